@@ -0,0 +1,64 @@
+//! Counts the allocations and time it takes to render a deep and wide tree, where most of the
+//! work is repeating the indent of each line, with siblings sharing their indent and with each
+//! line building its own as before. Run with `cargo bench --bench prefixes`.
+
+use branchify::tree_generator::{generate_tree_from_paths, Options, PathEntry};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+fn main() {
+    // 8 levels deep, with 40 files in each directory.
+    let mut paths = Vec::new();
+    let mut dir = String::new();
+    for depth in 0..8 {
+        dir.push_str(&format!("dir{}/", depth));
+        for file in 0..40 {
+            paths.push(PathEntry::new(&format!("{}file{}.rs", dir, file), "M"));
+        }
+    }
+    let shared = generate(&paths, "shared prefixes", &Options::default());
+    let unshared = generate(
+        &paths,
+        "a prefix per line (baseline)",
+        &Options {
+            unshared_prefixes: true,
+            ..Default::default()
+        },
+    );
+    assert_eq!(shared, unshared, "sharing prefixes changed the output");
+}
+
+fn generate(paths: &[PathEntry], label: &str, options: &Options) -> String {
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    let start = Instant::now();
+    let output = generate_tree_from_paths(paths, options);
+    let elapsed = start.elapsed();
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - before;
+    println!(
+        "{}: {} lines, {} allocations, {:?}",
+        label,
+        output.lines().count(),
+        allocations,
+        elapsed
+    );
+    output
+}
@@ -0,0 +1,99 @@
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+
+use crate::tree_generator::{generate_tree_from_paths, Options, PathEntry};
+
+/// Somewhere to put copied text, so tests can swap in a fake.
+pub trait Clipboard {
+    fn set_text(&mut self, text: &str) -> io::Result<()>;
+}
+
+/// The system clipboard, reached through the first of the platform's copy commands that runs.
+pub struct SystemClipboard;
+
+const COPY_COMMANDS: &[&[&str]] = if cfg!(windows) {
+    &[&["clip"]]
+} else if cfg!(target_os = "macos") {
+    &[&["pbcopy"]]
+} else {
+    &[
+        &["wl-copy"],
+        &["xclip", "-selection", "clipboard"],
+        &["xsel", "--clipboard", "--input"],
+    ]
+};
+
+impl Clipboard for SystemClipboard {
+    fn set_text(&mut self, text: &str) -> io::Result<()> {
+        for &command in COPY_COMMANDS {
+            let [program, args @ ..] = command else {
+                continue;
+            };
+            let child = Command::new(program)
+                .args(args)
+                .stdin(Stdio::piped())
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .spawn();
+            let Ok(mut child) = child else {
+                continue;
+            };
+            child.stdin.take().unwrap().write_all(text.as_bytes())?;
+            if child.wait()?.success() {
+                return Ok(());
+            }
+        }
+        Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            "no clipboard command is available",
+        ))
+    }
+}
+
+/// Copies the tree for `paths` to `clipboard` without colors, whatever `options` says.
+pub fn copy_tree(
+    clipboard: &mut impl Clipboard,
+    paths: &[PathEntry],
+    options: &Options,
+) -> io::Result<()> {
+    let plain = Options {
+        color: false,
+        ..options.clone()
+    };
+    clipboard.set_text(&generate_tree_from_paths(paths, &plain))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct FakeClipboard {
+        text: Option<String>,
+    }
+
+    impl Clipboard for FakeClipboard {
+        fn set_text(&mut self, text: &str) -> io::Result<()> {
+            self.text = Some(text.to_string());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_copy_tree_without_colors() {
+        let paths = [
+            PathEntry::new("src/main.rs", "M"),
+            PathEntry::new("README.md", "A"),
+        ];
+        let mut clipboard = FakeClipboard::default();
+        let options = Options {
+            color: true,
+            ..Default::default()
+        };
+        copy_tree(&mut clipboard, &paths, &options).unwrap();
+        assert_eq!(
+            clipboard.text.as_deref(),
+            Some("├── README.md\n└── src\n    └── main.rs\n")
+        );
+    }
+}
@@ -0,0 +1,82 @@
+use std::cmp::Ordering;
+
+/// Compares names the way a person would expect them listed, approximating the Unicode
+/// collation algorithm's default order without depending on the system locale: letters are
+/// compared without regard to accents and case first, then accents decide (unaccented first),
+/// then case (lowercase first), and finally the raw bytes.
+///
+/// Accents are only known for the Latin letters up to U+024F. Other characters compare by their
+/// code points, the same as the byte order.
+pub fn compare(a: &str, b: &str) -> Ordering {
+    primary_key(a)
+        .cmp(&primary_key(b))
+        .then_with(|| a.to_lowercase().cmp(&b.to_lowercase()))
+        .then_with(|| case_key(a).cmp(case_key(b)))
+        .then_with(|| a.cmp(b))
+}
+
+/// `s` in lowercase with accents removed, e.g. `Ärger` becomes `arger`.
+fn primary_key(s: &str) -> String {
+    let mut key = String::with_capacity(s.len());
+    for c in s.chars().flat_map(char::to_lowercase) {
+        match c {
+            'ß' => key.push_str("ss"),
+            'æ' => key.push_str("ae"),
+            'œ' => key.push_str("oe"),
+            'þ' => key.push_str("th"),
+            c => key.push(base_letter(c)),
+        }
+    }
+    key
+}
+
+/// Whether each character is uppercase, so that lowercase sorts first.
+fn case_key(s: &str) -> impl Iterator<Item = bool> + '_ {
+    s.chars().map(char::is_uppercase)
+}
+
+/// The unaccented letter of a lowercase Latin letter, or `c` itself.
+fn base_letter(c: char) -> char {
+    match c {
+        'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' | 'ā' | 'ă' | 'ą' | 'ǎ' | 'ǟ' | 'ǡ' | 'ǻ' | 'ȁ' | 'ȃ'
+        | 'ȧ' => 'a',
+        'ç' | 'ć' | 'ĉ' | 'ċ' | 'č' => 'c',
+        'ď' | 'đ' | 'ð' => 'd',
+        'è' | 'é' | 'ê' | 'ë' | 'ē' | 'ĕ' | 'ė' | 'ę' | 'ě' | 'ȅ' | 'ȇ' | 'ȩ' => 'e',
+        'ĝ' | 'ğ' | 'ġ' | 'ģ' | 'ǧ' | 'ǵ' => 'g',
+        'ĥ' | 'ȟ' | 'ħ' => 'h',
+        'ì' | 'í' | 'î' | 'ï' | 'ĩ' | 'ī' | 'ĭ' | 'į' | 'ǐ' | 'ȉ' | 'ȋ' | 'ı' => 'i',
+        'ĵ' | 'ǰ' => 'j',
+        'ķ' | 'ǩ' => 'k',
+        'ĺ' | 'ļ' | 'ľ' | 'ŀ' | 'ł' => 'l',
+        'ñ' | 'ń' | 'ņ' | 'ň' | 'ǹ' => 'n',
+        'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' | 'ō' | 'ŏ' | 'ő' | 'ơ' | 'ǒ' | 'ǫ' | 'ǭ' | 'ȍ' | 'ȏ'
+        | 'ȫ' | 'ȭ' | 'ȯ' | 'ȱ' => 'o',
+        'ŕ' | 'ŗ' | 'ř' | 'ȑ' | 'ȓ' => 'r',
+        'ś' | 'ŝ' | 'ş' | 'š' | 'ș' => 's',
+        'ţ' | 'ť' | 'ț' | 'ŧ' => 't',
+        'ù' | 'ú' | 'û' | 'ü' | 'ũ' | 'ū' | 'ŭ' | 'ů' | 'ű' | 'ų' | 'ư' | 'ǔ' | 'ǖ' | 'ǘ' | 'ǚ'
+        | 'ǜ' | 'ȕ' | 'ȗ' => 'u',
+        'ŵ' => 'w',
+        'ý' | 'ÿ' | 'ŷ' | 'ȳ' => 'y',
+        'ź' | 'ż' | 'ž' => 'z',
+        c => c,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compare() {
+        let mut names = vec![
+            "Zebra", "résumé", "eagle", "Ética", "resume", "apple", "Äpfel", "b", "B",
+        ];
+        names.sort_by(|a, b| compare(a, b));
+        assert_eq!(
+            names,
+            vec!["Äpfel", "apple", "b", "B", "eagle", "Ética", "resume", "résumé", "Zebra"]
+        );
+    }
+}
@@ -0,0 +1,116 @@
+use encoding_rs::{EncoderResult, Encoding, BIG5, EUC_JP, EUC_KR, GBK, SHIFT_JIS};
+
+/// An encoding for `--output-encoding`, for terminals and pipelines that do not expect UTF-8.
+/// The multi-byte ones are transcoded with `encoding_rs`.
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum OutputEncoding {
+    /// ISO-8859-1, which covers the accented letters of Western European languages.
+    Latin1,
+    /// 7-bit ASCII.
+    Ascii,
+    /// Shift_JIS, as used on Japanese Windows.
+    ShiftJis,
+    /// EUC-JP, the older Japanese encoding on Unix.
+    EucJp,
+    /// GBK, for Simplified Chinese.
+    Gbk,
+    /// Big5, for Traditional Chinese.
+    Big5,
+    /// EUC-KR, for Korean.
+    EucKr,
+}
+
+/// Written for each character that `OutputEncoding` cannot represent.
+pub const REPLACEMENT: u8 = b'?';
+
+impl OutputEncoding {
+    /// The highest code point a single-byte encoding maps to the byte of the same value.
+    fn max_char(self) -> Option<char> {
+        match self {
+            OutputEncoding::Latin1 => Some('\u{ff}'),
+            OutputEncoding::Ascii => Some('\u{7f}'),
+            _ => None,
+        }
+    }
+
+    fn multibyte(self) -> Option<&'static Encoding> {
+        match self {
+            OutputEncoding::ShiftJis => Some(SHIFT_JIS),
+            OutputEncoding::EucJp => Some(EUC_JP),
+            OutputEncoding::Gbk => Some(GBK),
+            OutputEncoding::Big5 => Some(BIG5),
+            OutputEncoding::EucKr => Some(EUC_KR),
+            _ => None,
+        }
+    }
+}
+
+/// Transcodes `s` to `encoding`, replacing characters it lacks with `REPLACEMENT`.
+pub fn encode(s: &str, encoding: OutputEncoding) -> Vec<u8> {
+    if let Some(multibyte) = encoding.multibyte() {
+        return encode_multibyte(s, multibyte);
+    }
+    let max_char = encoding.max_char().unwrap();
+    s.chars()
+        .map(|c| if c <= max_char { c as u8 } else { REPLACEMENT })
+        .collect()
+}
+
+fn encode_multibyte(s: &str, encoding: &'static Encoding) -> Vec<u8> {
+    let mut encoder = encoding.new_encoder();
+    let mut out = Vec::with_capacity(s.len());
+    let mut rest = s;
+    loop {
+        let (result, read) =
+            encoder.encode_from_utf8_to_vec_without_replacement(rest, &mut out, true);
+        rest = &rest[read..];
+        match result {
+            EncoderResult::InputEmpty => return out,
+            // `encoding_rs` would write an HTML character reference, which a terminal shows as is.
+            EncoderResult::Unmappable(_) => out.push(REPLACEMENT),
+            EncoderResult::OutputFull => out.reserve(rest.len().max(16)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree_generator::{generate_tree_from_paths, Options, PathEntry, TreeChars};
+
+    #[test]
+    fn test_encode_tree_as_latin1() {
+        let tree = generate_tree_from_paths(
+            &[
+                PathEntry::new("café/résumé.txt", ""),
+                PathEntry::new("日本.txt", ""),
+            ],
+            &Options {
+                tree_chars: TreeChars::Ascii,
+                ..Default::default()
+            },
+        );
+        assert_eq!(
+            encode(&tree, OutputEncoding::Latin1),
+            b"|-- caf\xe9\n|   `-- r\xe9sum\xe9.txt\n`-- ??.txt\n"
+        );
+    }
+
+    #[test]
+    fn test_encode_tree_as_shift_jis() {
+        let tree = generate_tree_from_paths(
+            &[PathEntry::new("日本.txt", ""), PathEntry::new("é.rs", "")],
+            &Options::default(),
+        );
+        assert_eq!(
+            encode(&tree, OutputEncoding::ShiftJis),
+            b"\x84\xa5\x84\x9f\x84\x9f ?.rs\n\x84\xa4\x84\x9f\x84\x9f \x93\xfa\x96{.txt\n"
+        );
+        assert_eq!(encode("日本", OutputEncoding::EucKr), b"\xec\xed\xdc\xe2");
+    }
+
+    #[test]
+    fn test_encode_ascii() {
+        assert_eq!(encode("├── é", OutputEncoding::Ascii), b"??? ?");
+    }
+}
@@ -0,0 +1,66 @@
+/// Opens the self-contained page that `--html-interactive` writes the tree into.
+pub const PAGE_START: &str = r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>branchify</title>
+<style>
+body { font-family: monospace; }
+ul { list-style: none; margin: 0; padding-left: 1.5em; }
+summary { cursor: pointer; color: #2f6fd6; }
+.modified { color: #b58900; }
+.added { color: #2aa150; }
+.deleted { color: #d33682; text-decoration: line-through; }
+.renamed { color: #1fa3a3; }
+.copied { color: #9c4fd1; }
+.unmerged { color: #dc322f; font-weight: bold; }
+.untracked { color: #888888; }
+</style>
+</head>
+<body>
+"#;
+
+pub const PAGE_END: &str = "</body>\n</html>\n";
+
+/// Appends `s` to `out` with the characters that are special in HTML text and attributes
+/// replaced by entities.
+pub fn write_escaped(out: &mut String, s: &str) {
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+}
+
+/// The CSS class of the page's stylesheet for a git status.
+pub fn status_class(status: &str) -> Option<&'static str> {
+    if status == "??" {
+        return Some("untracked");
+    }
+    match status.chars().next()? {
+        'M' | 'T' => Some("modified"),
+        'A' => Some("added"),
+        'D' => Some("deleted"),
+        'R' => Some("renamed"),
+        'C' => Some("copied"),
+        'U' => Some("unmerged"),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_escaped() {
+        let mut out = String::new();
+        write_escaped(&mut out, r#"<a href="x">&'</a>"#);
+        assert_eq!(out, "&lt;a href=&quot;x&quot;&gt;&amp;&#39;&lt;/a&gt;");
+    }
+}
@@ -0,0 +1,15 @@
+#[cfg(feature = "clipboard")]
+pub mod clipboard;
+pub mod collate;
+#[cfg(feature = "encoding")]
+pub mod encoding;
+pub mod html;
+pub mod json;
+pub mod ordered_map;
+pub mod palette;
+pub mod parser;
+pub mod regex;
+pub mod template;
+pub mod tree_generator;
+pub mod unicode;
+pub mod watch;
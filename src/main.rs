@@ -1,78 +1,1208 @@
 use clap::Parser;
-use std::io::{self, BufRead};
+use std::collections::{HashMap, HashSet};
+use std::io::{self, BufRead, Read, Write};
+use std::path::PathBuf;
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
 
-mod tree_generator;
-use tree_generator::{generate_tree_from_paths, Options};
+#[cfg(feature = "clipboard")]
+use branchify::clipboard::{copy_tree, SystemClipboard};
+#[cfg(feature = "encoding")]
+use branchify::encoding::{encode, OutputEncoding};
+use branchify::palette::{self, Palette};
+use branchify::parser::{
+    detect_format, parse_json_tree, parse_line, parse_lines, parse_lines_as, parse_status_codes,
+    parse_status_map, strip_ansi, validate_lines, Diagnostic, InputFormat,
+};
+use branchify::regex::Regex;
+use branchify::template::Template;
+use branchify::tree_generator::{
+    build_tree, color_test, find_path_conflicts, generate_tree_from_paths, render,
+    write_streamed_entry, write_tree_as_json_lines, ColorScope, ColumnMerge, CountMode,
+    LegendPosition, Options, PathCase, PathEntry, SortKey, SortOrder, StatusDisplay, TreeChars,
+};
+use branchify::unicode::NormalizationForm;
+use branchify::watch::{read_status_command, SystemRunner, Watcher, DEFAULT_WATCH_COMMAND};
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq)]
+enum ColorWhen {
+    Auto,
+    Always,
+    Never,
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq)]
+enum ErrorFormat {
+    Text,
+    /// One `{"line", "column", "expected", "text"}` object
+    Json,
+}
 
 #[derive(clap::Args, Debug)]
 struct Opts {
     #[arg(short, long)]
     pub compact: bool,
-    #[arg(long)]
-    pub color: bool,
+    /// When to color the output; a bare --color means always, even through a pipe
+    #[arg(
+        long,
+        value_name = "WHEN",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "always"
+    )]
+    pub color: Option<ColorWhen>,
     #[arg(long, name = "no-color")]
     pub no_color: bool,
+    /// Lay the top-level subtrees out side by side in N columns
+    #[arg(long, value_name = "N")]
+    pub columns: Option<usize>,
+    /// Merge directories whose names differ only in case
+    #[arg(long)]
+    pub ignore_case_dedup: bool,
+    /// Highlight the file at PATH, e.g. the one currently open in an editor
+    #[arg(long, value_name = "PATH")]
+    pub current: Option<String>,
+    /// Replace every name with a placeholder to inspect just the tree guides
+    #[arg(long)]
+    pub guides_only: bool,
+    /// List directories with the most changes first
+    #[arg(long)]
+    pub sort_dirs_by_count: bool,
+    /// List directories before files within each level
+    #[arg(long)]
+    pub dirs_first: bool,
+    /// List files before directories within each level
+    #[arg(long, conflicts_with_all = ["dirs_first", "sort_dirs_by_count"])]
+    pub files_first: bool,
+    /// Color files by age, from recent (bright) to old (dim), for timestamped input
+    #[arg(long, visible_alias = "blame-colors")]
+    pub age_gradient: bool,
+    /// Print the full path of each entry, one per line, instead of a tree
+    #[arg(long, conflicts_with_all = ["columns", "guides_only"])]
+    pub flat: bool,
+    /// Separate --flat paths with NUL instead of newlines, for `xargs -0`
+    #[arg(long, short = '0', requires = "flat")]
+    pub print0: bool,
+    /// Highlight directories that directly contain more than N entries
+    #[arg(long, value_name = "N")]
+    pub warn_dir_size: Option<usize>,
+    /// Strip the leading directories shared by every path
+    #[arg(long)]
+    pub trim_common_prefix: bool,
+    /// Print the tree as a nested JSON document
+    #[arg(long, conflicts_with_all = ["flat", "columns"])]
+    pub json: bool,
+    /// Wrap long file names onto continuation lines to fit within N columns
+    #[arg(long, value_name = "N")]
+    pub wrap: Option<usize>,
+    /// (experimental) List sibling files on shared comma-separated lines
+    #[arg(long)]
+    pub merge_files: bool,
+    /// Terminal width used by --merge-files [default: $COLUMNS or 80]
+    #[arg(long, value_name = "N")]
+    pub width: Option<usize>,
+    /// Treat both `/` and `\` as path separators
+    #[arg(long)]
+    pub mixed_separators: bool,
+    /// Print top-level entries at column 0 without a connector
+    #[arg(long)]
+    pub bare_root: bool,
+    /// Comma-separated submodule paths to show without their contents
+    #[arg(long, value_name = "LIST", value_delimiter = ',')]
+    pub submodules: Vec<String>,
+    /// Print a separate tree under a header for each top-level directory
+    #[arg(long, conflicts_with_all = ["flat", "json", "columns"])]
+    pub per_root: bool,
+    /// Show LABEL after the file at PATH; can be repeated
+    #[arg(long, value_name = "PATH=LABEL", value_parser = parse_annotation)]
+    pub annotate: Vec<(String, String)>,
+    /// Print each file's status after its name
+    #[arg(long, value_name = "MODE")]
+    pub status_display: Option<StatusDisplay>,
+    /// Stream one JSON object per file, with its path, status and depth, per line
+    #[arg(long, conflicts_with_all = ["flat", "json", "columns", "per_root"])]
+    pub jsonl: bool,
+    /// Emphasize directories on the way to a change and dim the others
+    #[arg(long)]
+    pub highlight_dirty_path: bool,
+    /// Print one line per file using TEMPLATE, with {path}, {name}, {status} and {depth}
+    #[arg(
+        long,
+        value_name = "TEMPLATE",
+        conflicts_with_all = ["json", "jsonl", "columns", "per_root"]
+    )]
+    pub format: Option<Template>,
+    /// Hide the levels above depth N (top-level entries are at depth 1)
+    #[arg(long, value_name = "N")]
+    pub min_depth: Option<usize>,
+    /// Remove directories whose only child is a directory, moving the child up a level
+    #[arg(long, conflicts_with = "compact")]
+    pub flatten_single: bool,
+    /// Print an HTML page with collapsible directories
+    #[arg(long, conflicts_with_all = ["flat", "json", "jsonl", "columns", "per_root", "format"])]
+    pub html_interactive: bool,
+    /// Order entries by the importance of their status, the same as `--sort-key status`
+    #[arg(long, conflicts_with = "sort_key")]
+    pub sort_by_status: bool,
+    /// Show the most important status inside each directory after its name
+    #[arg(long)]
+    pub dir_status: bool,
+    /// Comma-separated status codes from most to least important, e.g. 'U,A,M,D,??'
+    #[arg(long, value_name = "ORDER", value_delimiter = ',')]
+    pub status_priority: Vec<String>,
+    /// Show files sharing an extension within a directory as one `*.ext (N)` entry
+    #[arg(long)]
+    pub fold_extensions: bool,
+    /// Undo --fold-extensions, e.g. one set in a shell alias
+    #[arg(long, overrides_with = "fold_extensions")]
+    pub no_fold: bool,
+    /// How to order the names within each directory
+    #[arg(long, value_name = "ORDER", default_value = "name")]
+    pub sort: SortOrder,
+    /// With --compact, only join chains of at least N directories
+    #[arg(long, value_name = "N", requires = "compact")]
+    pub compact_threshold: Option<usize>,
+    /// Only show paths with one of these comma-separated status codes, e.g. 'M,??'
+    #[arg(long, value_name = "CODES", value_delimiter = ',')]
+    pub only: Vec<String>,
+    /// Only show modified paths; combines with the other status filters
+    #[arg(long)]
+    pub modified: bool,
+    /// Only show added paths; combines with the other status filters
+    #[arg(long)]
+    pub added: bool,
+    /// Only show deleted paths; combines with the other status filters
+    #[arg(long)]
+    pub deleted: bool,
+    /// Only show untracked paths; combines with the other status filters
+    #[arg(long)]
+    pub untracked: bool,
+    /// Only show paths with merge conflicts; combines with the other status filters
+    #[arg(long)]
+    pub conflicts: bool,
+    /// Stop after N lines of output; 0 prints everything
+    #[arg(long, value_name = "N", conflicts_with_all = ["json", "jsonl", "html_interactive"])]
+    pub max_total_lines: Option<usize>,
+    /// Explain the statuses that appear in the tree
+    #[arg(long)]
+    pub legend: bool,
+    /// Where to print the --legend and --icon-legend
+    #[arg(
+        long,
+        value_name = "POSITION",
+        default_value = "bottom",
+        requires = "legends"
+    )]
+    pub legend_position: LegendPosition,
+    /// Read statuses for the input paths from FILE, one `<path><TAB><status>` per line
+    #[arg(long, value_name = "FILE")]
+    pub status_file: Option<PathBuf>,
+    /// Color the parts of compacted directory names like `after/lsp` in alternating shades
+    #[arg(long, visible_alias = "collapse-compact-colorize")]
+    pub segment_colors: bool,
+    /// Leave out dotfiles and dot-directories, like `ls` without `-a`
+    #[arg(long)]
+    pub hide_hidden: bool,
+    /// Show dotfiles and dot-directories (the default), undoing --hide-hidden
+    #[arg(long, overrides_with = "hide_hidden")]
+    pub show_hidden: bool,
+    /// Follow the tree with the number of files per extension; with --json, print only those
+    #[arg(long, conflicts_with_all = ["flat", "jsonl", "format", "html_interactive"])]
+    pub ext_summary: bool,
+    /// Remove directories that the filters leave with nothing in them (the default)
+    #[arg(long, overrides_with = "no_prune_empty")]
+    pub prune_empty: bool,
+    /// Keep directories that the filters leave with nothing in them
+    #[arg(long)]
+    pub no_prune_empty: bool,
+    /// Also give files with a status a background in their status color
+    #[arg(long, visible_alias = "color-status-bg")]
+    pub status_bg: bool,
+    /// The characters to draw the tree with
+    #[arg(long, value_name = "STYLE", default_value = "utf8")]
+    pub tree_chars: TreeChars,
+    /// Instead of the tree, print one line per top-level directory with its status counts
+    #[arg(
+        long,
+        conflicts_with_all = ["flat", "jsonl", "format", "html_interactive", "per_root", "columns", "ext_summary"]
+    )]
+    pub dashboard: bool,
+    /// Keep the paths in input order, the same as `--sort input`
+    #[arg(long, conflicts_with = "sort")]
+    pub no_sort: bool,
+    /// Mark files with a `●` in their status color instead of coloring their names
+    #[arg(long)]
+    pub status_glyphs: bool,
+    /// Make files ending in .EXT bold and underlined; can be repeated
+    #[arg(long = "highlight-extension", value_name = "EXT")]
+    pub highlight_extensions: Vec<String>,
+    /// Follow each directory with the number of files below it
+    #[arg(long)]
+    pub counts: bool,
+    /// Like --counts, counting files
+    #[arg(long, conflicts_with_all = ["count_dirs", "count_all"])]
+    pub count_files: bool,
+    /// Like --counts, counting directories
+    #[arg(long, conflicts_with = "count_all")]
+    pub count_dirs: bool,
+    /// Like --counts, counting files and directories
+    #[arg(long)]
+    pub count_all: bool,
+    /// Normalize paths to FORM, merging names that differ only in how accents are encoded
+    #[arg(long, value_name = "FORM")]
+    pub normalize_unicode: Option<NormalizationForm>,
+    /// Print folded stacks for flame graph tools, e.g. `src;main.rs 15`, instead of a tree
+    #[arg(
+        long,
+        conflicts_with_all = ["flat", "json", "jsonl", "format", "html_interactive", "columns", "per_root", "dashboard"]
+    )]
+    pub folded: bool,
+    /// Show directories whose name or path matches PATTERN as one line with an entry count,
+    /// e.g. 'node_modules|target'
+    #[arg(long = "collapse-by-regex", value_name = "PATTERN")]
+    pub collapse_pattern: Option<Regex>,
+    /// Print a line counting each status above the tree, e.g. `● 2M 1A 1D 3??`
+    #[arg(long)]
+    pub root_status: bool,
+    /// Put a guide line above and below the entries of each directory
+    #[arg(long, visible_alias = "wrap-directories")]
+    pub brackets: bool,
+    /// Show at most N files and N directories per directory, summarizing the rest as `+7 more files`
+    #[arg(long, value_name = "N")]
+    pub max_siblings: Option<usize>,
+    /// Show the leading path component FROM as TO, or drop it if TO is `.`; can be repeated
+    #[arg(long = "alias", value_name = "FROM=TO", value_parser = parse_alias)]
+    pub aliases: Vec<(String, String)>,
+    /// Apply --alias to every path component, not just the first
+    #[arg(long)]
+    pub alias_all: bool,
+    /// What to order each directory by first, before --sort breaks ties
+    #[arg(long, value_name = "KEY", default_value = "name")]
+    pub sort_key: SortKey,
+    /// Reverse the order of each directory; --dirs-first and --files-first still apply
+    #[arg(long)]
+    pub reverse: bool,
+    /// (debug) Follow each name with its depth, e.g. `lua [d2]`
+    #[arg(long, visible_alias = "show-path-depth")]
+    pub show_depth: bool,
+    /// Join --compact directory names with STR instead of `/`, e.g. ' › '
+    #[arg(long, value_name = "STR", requires = "compact")]
+    pub compact_separator: Option<String>,
+    /// Read what other tools' status codes mean from FILE, one `CODE=CATEGORY` per line, e.g.
+    /// `changed=modified`
+    #[arg(long, value_name = "FILE")]
+    pub status_map: Option<PathBuf>,
+    /// Put a Nerd Font glyph for its type before each file name
+    #[arg(long)]
+    pub icons: bool,
+    /// List the --icons glyphs of the extensions in the tree
+    #[arg(long, requires = "icons")]
+    pub icon_legend: bool,
+    /// Show names in lower or upper case, without changing the tree or its order
+    #[arg(long, value_name = "CASE", default_value = "preserve")]
+    pub path_case: PathCase,
+    /// Follow each file with its changed lines and a `+++--` bar scaled to the largest change
+    #[arg(long, visible_alias = "diff-stat-bars")]
+    pub bars: bool,
+    /// List untracked and ignored entries after all the others in each directory
+    #[arg(long, visible_alias = "group-untracked-last")]
+    pub untracked_last: bool,
+    /// Read the colors of statuses, directories, guides and highlighted files from a JSON FILE,
+    /// e.g. '{"statuses": {"M": "#ff8800"}, "directory": "cyan", "connector": 244}'
+    #[arg(long, value_name = "FILE")]
+    pub palette: Option<PathBuf>,
+    /// Whether a one-letter --only code matches a two-letter status like `MM` when either column
+    /// has it (or) or only when both do (and)
+    #[arg(long, value_name = "MODE", default_value = "or")]
+    pub merge_status_columns: ColumnMerge,
+    /// What --color applies to: names, all (names and tree guides) or status-only (status
+    /// glyphs and annotations)
+    #[arg(long, value_name = "SCOPE", default_value = "all")]
+    pub color_scope: ColorScope,
+    /// List only changed files, each with its full path and status, without the directories
+    /// above them
+    #[arg(
+        long,
+        conflicts_with_all = ["flat", "json", "jsonl", "columns", "per_root", "format", "html_interactive", "dashboard"]
+    )]
+    pub tree_only_changed_leaves: bool,
+}
+
+impl Opts {
+    /// What --counts or one of its variants asked to count, if any.
+    fn count_mode(&self) -> Option<CountMode> {
+        if self.count_dirs {
+            Some(CountMode::Dirs)
+        } else if self.count_all {
+            Some(CountMode::All)
+        } else if self.counts || self.count_files {
+            Some(CountMode::Files)
+        } else {
+            None
+        }
+    }
+
+    /// The status codes of --only and the convenience filters together.
+    fn only(&self) -> Vec<String> {
+        let mut only = self.only.clone();
+        let flags = [
+            (self.modified, &["M"][..]),
+            (self.added, &["A"]),
+            (self.deleted, &["D"]),
+            (self.untracked, &["??"]),
+            (self.conflicts, &["U", "AA", "DD"]),
+        ];
+        for (enabled, codes) in flags {
+            if enabled {
+                only.extend(codes.iter().map(|code| code.to_string()));
+            }
+        }
+        only
+    }
 }
 
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
+#[command(group(clap::ArgGroup::new("streamable").args(["flat", "jsonl"])))]
+#[command(group(clap::ArgGroup::new("legends").args(["legend", "icon_legend"]).multiple(true)))]
 struct Args {
     #[command(flatten)]
     options: Opts,
+    /// Instead of reading stdin, re-run --watch-command every SECS seconds and redraw the tree
+    #[arg(long, value_name = "SECS", num_args = 0..=1, default_missing_value = "2")]
+    watch: Option<u64>,
+    /// Command whose output is rendered in --watch mode
+    #[arg(long, value_name = "CMD", default_value = DEFAULT_WATCH_COMMAND)]
+    watch_command: String,
+    /// Run TEMPLATE, e.g. 'git -C {repo} status --porcelain', and read its output instead of
+    /// stdin. It is split into words like a shell would, but no shell runs it
+    #[arg(
+        long,
+        value_name = "TEMPLATE",
+        conflicts_with_all = ["watch", "stream", "to_paths", "stdin_timeout"]
+    )]
+    status_cmd: Option<String>,
+    /// The directory that {repo} in --status-cmd stands for
+    #[arg(long, value_name = "DIR", default_value = ".", requires = "status_cmd")]
+    repo: String,
+    /// Fail if a path is left out because a file and a directory share its name
+    #[arg(long)]
+    strict: bool,
+    /// Tell how many paths were left out because a file and a directory share their name
+    #[arg(long)]
+    report_conflicts: bool,
+    /// Keep ANSI escape sequences in the input instead of removing them
+    #[arg(long)]
+    no_strip_ansi: bool,
+    /// Skip input lines longer than N bytes, with a warning
+    #[arg(long, value_name = "N")]
+    max_path_length: Option<usize>,
+    /// Read the input as FORMAT instead of detecting it from the first line
+    #[arg(long, value_name = "FORMAT")]
+    input_format: Option<InputFormat>,
+    /// With --flat or --jsonl, print each path as soon as it is read, in input order
+    #[arg(long, requires = "streamable")]
+    stream: bool,
+    /// Print how long parsing, building and rendering the tree took to stderr
+    #[arg(long, conflicts_with = "jsonl")]
+    render_time: bool,
+    /// Write the tree in a legacy encoding like latin1 or shift-jis instead of UTF-8, with `?` for
+    /// characters it lacks
+    #[cfg(feature = "encoding")]
+    #[arg(long, value_name = "ENCODING", conflicts_with = "jsonl")]
+    output_encoding: Option<OutputEncoding>,
+    /// Also copy the tree, without colors, to the clipboard
+    #[cfg(feature = "clipboard")]
+    #[arg(long)]
+    copy: bool,
+    /// Give up if nothing arrives on stdin within MS milliseconds, instead of waiting forever
+    #[arg(long, value_name = "MS")]
+    stdin_timeout: Option<u64>,
+    /// Print MESSAGE, e.g. 'No changes', instead of nothing when the tree is empty
+    #[arg(long, value_name = "MESSAGE")]
+    on_empty: Option<String>,
+    /// Read a --json tree from stdin and print the paths it was built from, one per line
+    #[arg(long, conflicts_with_all = ["input_format", "stream", "watch"])]
+    to_paths: bool,
+    /// Read the input as `git status --porcelain` and fail on the first line that is not
+    /// `XY path`, instead of skipping it
+    #[arg(long, conflicts_with_all = ["input_format", "stream", "to_paths"])]
+    strict_porcelain: bool,
+    /// Fail on the first input line that does not fit the input format, instead of skipping it
+    #[arg(long, conflicts_with_all = ["stream", "to_paths"])]
+    strict_input: bool,
+    /// How --strict-input and --strict-porcelain report a malformed line on stderr
+    #[arg(long, value_name = "FORMAT", default_value = "text")]
+    error_format: ErrorFormat,
+    /// Drop input lines that repeat an earlier one, telling how many on stderr
+    #[arg(long, conflicts_with_all = ["stream", "to_paths"])]
+    deduplicate_paths: bool,
+    /// Stop writing the tree once it reaches BYTES bytes, ending with a note that it was cut off
+    #[arg(long, value_name = "BYTES", conflicts_with = "to_paths")]
+    render_budget: Option<usize>,
+    /// Print a built-in tree using every status color and exit
+    #[arg(long, hide = true)]
+    color_test: bool,
 }
 
 fn main() {
     let args = Args::parse();
+    if args.options.color == Some(ColorWhen::Always) {
+        // `colored` turns itself off when stdout is not a terminal, e.g. for `| less -R`.
+        colored::control::set_override(true);
+    }
+    let only = args.options.only();
+    let counts = args.options.count_mode();
+    let status_map = match &args.options.status_file {
+        Some(file) => match std::fs::read_to_string(file) {
+            Ok(contents) => parse_status_map(&contents),
+            Err(err) => {
+                eprintln!("branchify: failed to read `{}`: {}", file.display(), err);
+                std::process::exit(1);
+            }
+        },
+        None => HashMap::new(),
+    };
+    let status_codes = match &args.options.status_map {
+        Some(file) => match std::fs::read_to_string(file).map_err(|err| err.to_string()) {
+            Ok(contents) => parse_status_codes(&contents).unwrap_or_else(|err| {
+                eprintln!("branchify: `{}`: {}", file.display(), err);
+                std::process::exit(1);
+            }),
+            Err(err) => {
+                eprintln!("branchify: failed to read `{}`: {}", file.display(), err);
+                std::process::exit(1);
+            }
+        },
+        None => HashMap::new(),
+    };
+    let mut palette = match &args.options.palette {
+        Some(file) => match std::fs::read_to_string(file).map_err(|err| err.to_string()) {
+            Ok(contents) => palette::parse(&contents).unwrap_or_else(|err| {
+                eprintln!("branchify: `{}`: {}", file.display(), err);
+                std::process::exit(1);
+            }),
+            Err(err) => {
+                eprintln!("branchify: failed to read `{}`: {}", file.display(), err);
+                std::process::exit(1);
+            }
+        },
+        None => Palette::default(),
+    };
+    palette.truecolor = matches!(
+        std::env::var("COLORTERM").as_deref(),
+        Ok("truecolor" | "24bit")
+    );
     let opts = Options {
         compact: args.options.compact,
-        color: args.options.color || !args.options.no_color,
+        color: args.options.color != Some(ColorWhen::Never) && !args.options.no_color,
+        columns: args.options.columns,
+        ignore_case_dedup: args.options.ignore_case_dedup,
+        current: args.options.current,
+        guides_only: args.options.guides_only,
+        sort_dirs_by_count: args.options.sort_dirs_by_count,
+        dirs_first: args.options.dirs_first,
+        files_first: args.options.files_first,
+        age_gradient: args.options.age_gradient,
+        flat: args.options.flat,
+        print0: args.options.print0,
+        warn_dir_size: args.options.warn_dir_size,
+        trim_common_prefix: args.options.trim_common_prefix,
+        json: args.options.json,
+        wrap: args.options.wrap,
+        merge_files: args.options.merge_files,
+        width: args
+            .options
+            .width
+            .or_else(|| std::env::var("COLUMNS").ok()?.parse().ok()),
+        mixed_separators: args.options.mixed_separators,
+        bare_root: args.options.bare_root,
+        submodules: args.options.submodules,
+        per_root: args.options.per_root,
+        annotations: args.options.annotate.into_iter().collect(),
+        status_display: args.options.status_display,
+        jsonl: args.options.jsonl,
+        highlight_dirty_path: args.options.highlight_dirty_path,
+        format: args.options.format,
+        min_depth: args.options.min_depth,
+        flatten_single: args.options.flatten_single,
+        html_interactive: args.options.html_interactive,
+        sort_key: if args.options.sort_by_status {
+            SortKey::Status
+        } else {
+            args.options.sort_key
+        },
+        dir_status: args.options.dir_status,
+        status_priority: args.options.status_priority,
+        fold_extensions: args.options.fold_extensions && !args.options.no_fold,
+        sort: if args.options.no_sort {
+            SortOrder::Input
+        } else {
+            args.options.sort
+        },
+        compact_threshold: args.options.compact_threshold,
+        only,
+        max_total_lines: args.options.max_total_lines,
+        legend: args.options.legend.then_some(args.options.legend_position),
+        status_map,
+        segment_colors: args.options.segment_colors,
+        hide_hidden: args.options.hide_hidden && !args.options.show_hidden,
+        ext_summary: args.options.ext_summary,
+        keep_empty_dirs: args.options.no_prune_empty,
+        status_bg: args.options.status_bg,
+        tree_chars: args.options.tree_chars,
+        dashboard: args.options.dashboard,
+        status_glyphs: args.options.status_glyphs,
+        counts,
+        normalize_unicode: args.options.normalize_unicode,
+        folded: args.options.folded,
+        collapse_pattern: args.options.collapse_pattern,
+        root_status: args.options.root_status,
+        brackets: args.options.brackets,
+        max_siblings: args.options.max_siblings,
+        aliases: args.options.aliases,
+        alias_all: args.options.alias_all,
+        reverse: args.options.reverse,
+        show_depth: args.options.show_depth,
+        compact_separator: args.options.compact_separator,
+        status_codes,
+        icons: args.options.icons,
+        icon_legend: args
+            .options
+            .icon_legend
+            .then_some(args.options.legend_position),
+        path_case: args.options.path_case,
+        bars: args.options.bars,
+        untracked_last: args.options.untracked_last,
+        palette,
+        merge_status_columns: args.options.merge_status_columns,
+        color_scope: args.options.color_scope,
+        tree_only_changed_leaves: args.options.tree_only_changed_leaves,
+        unshared_prefixes: false,
+        highlight_extensions: args
+            .options
+            .highlight_extensions
+            .iter()
+            .map(|extension| extension.trim_start_matches('.').to_string())
+            .collect(),
     };
 
-    let lines: Vec<String> = io::stdin().lock().lines().map_while(Result::ok).collect();
+    if args.color_test {
+        print!("{}", color_test(&opts));
+        return;
+    }
 
-    // Heuristic to check if the input is likely from `git status --porcelain`
-    // It checks for two status characters followed by a space, e.g., "M  file.txt"
-    let is_porcelain_output = lines.first().map_or(false, |line| {
-        if line.len() < 4 {
-            return false;
+    if let Some(secs) = args.watch {
+        let mut watcher = Watcher {
+            command_line: &args.watch_command,
+            options: &opts,
+            runner: SystemRunner,
+        };
+        loop {
+            if let Err(err) = watcher.render_frame(&mut io::stdout()) {
+                eprintln!("branchify: failed to run `{}`: {}", args.watch_command, err);
+                std::process::exit(1);
+            }
+            thread::sleep(Duration::from_secs(secs));
         }
-        let status_part = &line[..2];
-        let separator = &line[2..3];
-        // Status part should not be empty after trim, and separator must be a space.
-        !status_part.trim().is_empty() && separator == " "
-    });
+    }
 
-    let paths_with_status: Vec<(String, String)> = if is_porcelain_output {
-        lines
-            .iter()
-            .filter_map(|line| {
-                if line.len() < 4 {
-                    return None;
+    let mut stdin: Box<dyn BufRead> = match args.stdin_timeout {
+        Some(ms) => {
+            // A locked stdin cannot move to the thread that waits on it.
+            let stdin = io::BufReader::new(io::stdin());
+            match wait_for_input(stdin, Duration::from_millis(ms)) {
+                Some(stdin) => Box::new(stdin),
+                None => {
+                    eprintln!(
+                        "branchify: no input after {} ms; pipe paths in, e.g. `git status --porcelain | branchify`",
+                        ms
+                    );
+                    std::process::exit(1);
                 }
-                let status_str = &line[..2];
-                let path_str = &line[3..];
+            }
+        }
+        None => Box::new(io::stdin().lock()),
+    };
 
-                let status = status_str.trim();
-                if status.is_empty() {
-                    return None;
-                }
+    if args.to_paths {
+        let mut input = String::new();
+        let paths = stdin
+            .read_to_string(&mut input)
+            .map_err(|err| err.to_string())
+            .and_then(|_| parse_json_tree(&input));
+        match paths {
+            Ok(paths) => print!("{}", path_list(paths)),
+            Err(err) => {
+                eprintln!("branchify: not a --json tree: {}", err);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
 
-                // For renames "R  old -> new", we want to display the new path
-                if status.starts_with('R') {
-                    if let Some(separator) = path_str.find(" -> ") {
-                        let new_path = path_str.split_at(separator + 4).1;
-                        return Some((new_path.to_string(), status.to_string()));
-                    }
-                }
-                Some((path_str.to_string(), status.to_string()))
-            })
-            .collect()
+    if args.stream {
+        let result = stream_paths(
+            stdin,
+            &mut stdout(args.render_budget),
+            &mut io::stderr(),
+            args.input_format,
+            args.max_path_length,
+            !args.no_strip_ansi,
+            &opts,
+        );
+        if let Err(err) = result {
+            eprintln!("branchify: {}", err);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let mut lines = match &args.status_cmd {
+        Some(template) => read_status_command(&mut SystemRunner, template, &args.repo)
+            .map(|output| read_lines(output.as_bytes(), args.max_path_length, &mut io::stderr()))
+            .unwrap_or_else(|err| {
+                eprintln!("branchify: failed to run `{}`: {}", template, err);
+                std::process::exit(1);
+            }),
+        None => read_lines(stdin, args.max_path_length, &mut io::stderr()),
+    };
+    if !args.no_strip_ansi && lines.iter().any(|line| line.contains('\u{1b}')) {
+        // Colored input, e.g. from `git status --color=always`.
+        lines = lines.iter().map(|line| strip_ansi(line)).collect();
+    }
+    if args.deduplicate_paths {
+        let removed = deduplicate_lines(&mut lines);
+        if removed > 0 {
+            eprintln!("branchify: removed {} duplicate line(s)", removed);
+        }
+    }
+    let input_format = if args.strict_porcelain {
+        Some(InputFormat::Porcelain)
     } else {
-        lines
-            .iter()
-            .map(|line| (line.clone(), String::new()))
-            .collect()
+        args.input_format
     };
+    let parse_start = Instant::now();
+    if args.strict_porcelain || args.strict_input {
+        let format = input_format.unwrap_or_else(|| match lines.first() {
+            Some(first) => detect_format(first),
+            None => InputFormat::Plain,
+        });
+        if let Err(diagnostic) = validate_lines(&lines, format) {
+            eprintln!("{}", format_diagnostic(&diagnostic, args.error_format));
+            std::process::exit(1);
+        }
+    }
+    let paths = match input_format {
+        Some(format) => parse_lines_as(&lines, format),
+        None => parse_lines(&lines),
+    };
+    let parse_time = parse_start.elapsed();
+
+    if args.strict || args.report_conflicts {
+        let conflicts = find_path_conflicts(&paths, &opts);
+        if args.strict && !conflicts.is_empty() {
+            for conflict in &conflicts {
+                eprintln!("branchify: {}", conflict);
+            }
+            std::process::exit(1);
+        }
+        if !conflicts.is_empty() {
+            eprintln!(
+                "branchify: left out {} conflicting path(s)",
+                conflicts.len()
+            );
+        }
+    }
+
+    if opts.jsonl {
+        // Written straight to stdout rather than collected into one string first.
+        let mut out = io::BufWriter::new(stdout(args.render_budget));
+        if let Err(err) =
+            write_tree_as_json_lines(&mut out, &paths, &opts).and_then(|_| out.flush())
+        {
+            eprintln!("branchify: {}", err);
+            std::process::exit(1);
+        }
+        return;
+    }
+    #[cfg(feature = "clipboard")]
+    if args.copy {
+        if let Err(err) = copy_tree(&mut SystemClipboard, &paths, &opts) {
+            eprintln!("branchify: could not copy to the clipboard: {}", err);
+        }
+    }
+
+    let output = if args.render_time {
+        generate_tree_timed(&paths, &opts, parse_time, &mut io::stderr())
+    } else {
+        generate_tree_from_paths(&paths, &opts)
+    };
+    let output = or_on_empty(output, args.on_empty.as_deref());
+    #[cfg(feature = "encoding")]
+    if let Some(encoding) = args.output_encoding {
+        let mut out = stdout(args.render_budget);
+        if let Err(err) = out
+            .write_all(&encode(&output, encoding))
+            .and_then(|_| out.flush())
+        {
+            eprintln!("branchify: {}", err);
+            std::process::exit(1);
+        }
+        return;
+    }
+    let mut out = stdout(args.render_budget);
+    if let Err(err) = out.write_all(output.as_bytes()).and_then(|_| out.flush()) {
+        eprintln!("branchify: {}", err);
+        std::process::exit(1);
+    }
+}
+
+/// Standard output, cut off after `budget` bytes if there is one.
+fn stdout(budget: Option<usize>) -> Box<dyn Write> {
+    match budget {
+        Some(budget) => Box::new(BudgetWriter::new(io::stdout().lock(), budget)),
+        None => Box::new(io::stdout().lock()),
+    }
+}
+
+/// Passes whole lines on to `inner` for as long as they fit in `budget` bytes. The first line
+/// that does not is replaced by a note saying where the output was cut off, and everything
+/// after it is dropped.
+struct BudgetWriter<W: Write> {
+    inner: W,
+    budget: usize,
+    written: usize,
+    /// The start of a line whose end has not been written yet.
+    pending: Vec<u8>,
+    exhausted: bool,
+}
+
+impl<W: Write> BudgetWriter<W> {
+    fn new(inner: W, budget: usize) -> Self {
+        BudgetWriter {
+            inner,
+            budget,
+            written: 0,
+            pending: Vec::new(),
+            exhausted: false,
+        }
+    }
+
+    fn write_line(&mut self, line: &[u8]) -> io::Result<()> {
+        if self.written + line.len() <= self.budget {
+            self.written += line.len();
+            return self.inner.write_all(line);
+        }
+        self.exhausted = true;
+        writeln!(self.inner, "… output cut off at {} bytes", self.budget)
+    }
+}
+
+impl<W: Write> Write for BudgetWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.pending.extend_from_slice(buf);
+        while let Some(end) = self.pending.iter().position(|&b| b == b'\n') {
+            if self.exhausted {
+                break;
+            }
+            let line: Vec<u8> = self.pending.drain(..=end).collect();
+            self.write_line(&line)?;
+        }
+        if self.exhausted {
+            self.pending.clear();
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if !self.pending.is_empty() && !self.exhausted {
+            let line = std::mem::take(&mut self.pending);
+            self.write_line(&line)?;
+        }
+        self.inner.flush()
+    }
+}
+
+/// `generate_tree_from_paths`, telling `timings` how long each phase took, `parse_time` being
+/// the time it took to parse `paths`.
+fn generate_tree_timed(
+    paths: &[PathEntry],
+    options: &Options,
+    parse_time: Duration,
+    timings: &mut impl Write,
+) -> String {
+    let build_start = Instant::now();
+    let tree = build_tree(paths, options);
+    let render_start = Instant::now();
+    let output = render(&tree, options);
+    let _ = writeln!(
+        timings,
+        "branchify: parse {:?}, build {:?}, render {:?}",
+        parse_time,
+        render_start - build_start,
+        render_start.elapsed()
+    );
+    output
+}
+
+/// Waits up to `timeout` for `input` to have something to read, or to end, and hands it back.
+/// `None` means it timed out; the thread still waiting on `input` is left behind.
+fn wait_for_input<R: BufRead + Send + 'static>(mut input: R, timeout: Duration) -> Option<R> {
+    let (ready, ready_rx) = mpsc::channel();
+    let reader = thread::spawn(move || {
+        let _ = input.fill_buf();
+        let _ = ready.send(());
+        input
+    });
+    ready_rx.recv_timeout(timeout).ok()?;
+    reader.join().ok()
+}
+
+/// Removes the lines of `lines` that are the same as an earlier one, keeping the order of the
+/// rest, and returns how many it removed.
+fn deduplicate_lines(lines: &mut Vec<String>) -> usize {
+    let mut seen = HashSet::new();
+    let len = lines.len();
+    lines.retain(|line| seen.insert(line.clone()));
+    len - lines.len()
+}
+
+/// `diagnostic` the way `--error-format` asks for it.
+fn format_diagnostic(diagnostic: &Diagnostic, format: ErrorFormat) -> String {
+    match format {
+        ErrorFormat::Text => format!("branchify: {}", diagnostic),
+        ErrorFormat::Json => diagnostic.to_json(),
+    }
+}
+
+/// The paths of `entries`, sorted and one per line.
+fn path_list(mut entries: Vec<PathEntry>) -> String {
+    entries.sort_by(|a, b| a.path.cmp(&b.path));
+    entries.into_iter().map(|entry| entry.path + "\n").collect()
+}
+
+/// `output`, or `message` on a line of its own if nothing was rendered. An empty `message`
+/// leaves the output empty.
+fn or_on_empty(output: String, message: Option<&str>) -> String {
+    match message {
+        Some(message) if output.is_empty() && !message.is_empty() => format!("{}\n", message),
+        _ => output,
+    }
+}
+
+/// Reads the lines of `input`, leaving out those longer than `max_len` bytes and telling
+/// `warnings` about each of them.
+fn read_lines(
+    input: impl BufRead,
+    max_len: Option<usize>,
+    warnings: &mut impl Write,
+) -> Vec<String> {
+    let mut lines = Vec::new();
+    for (i, line) in input.lines().map_while(Result::ok).enumerate() {
+        if !is_too_long(&line, i, max_len, warnings) {
+            lines.push(line);
+        }
+    }
+    lines
+}
+
+/// Whether the `i`th line (from 0) is longer than `max_len` bytes, telling `warnings` if so.
+fn is_too_long(line: &str, i: usize, max_len: Option<usize>, warnings: &mut impl Write) -> bool {
+    match max_len {
+        Some(max_len) if line.len() > max_len => {
+            let _ = writeln!(
+                warnings,
+                "branchify: skipped line {}, which is longer than {} bytes",
+                i + 1,
+                max_len
+            );
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Prints the path of each line of `input` as soon as the line is read, for `--stream`. The
+/// format is detected from the first line unless `format` is given.
+fn stream_paths(
+    input: impl BufRead,
+    out: &mut impl Write,
+    warnings: &mut impl Write,
+    mut format: Option<InputFormat>,
+    max_len: Option<usize>,
+    strip_escapes: bool,
+    options: &Options,
+) -> io::Result<()> {
+    for (i, line) in input.lines().map_while(Result::ok).enumerate() {
+        if is_too_long(&line, i, max_len, warnings) {
+            continue;
+        }
+        let line = if strip_escapes && line.contains('\u{1b}') {
+            strip_ansi(&line)
+        } else {
+            line
+        };
+        let format = *format.get_or_insert_with(|| detect_format(&line));
+        if let Some(entry) = parse_line(&line, format) {
+            write_streamed_entry(out, &entry, options)?;
+            out.flush()?;
+        }
+    }
+    Ok(())
+}
+
+fn parse_annotation(arg: &str) -> Result<(String, String), String> {
+    let (path, label) = arg
+        .split_once('=')
+        .ok_or_else(|| format!("expected PATH=LABEL, got `{}`", arg))?;
+    Ok((path.to_string(), label.to_string()))
+}
+
+fn parse_alias(arg: &str) -> Result<(String, String), String> {
+    match arg.split_once('=') {
+        Some((from, to)) if !from.is_empty() && !to.is_empty() => {
+            Ok((from.to_string(), to.to_string()))
+        }
+        _ => Err(format!("expected FROM=TO, got `{}`", arg)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use std::io::Read;
+    use std::rc::Rc;
+
+    #[test]
+    fn test_status_filter_flags_accumulate() {
+        let args = Args::try_parse_from(["branchify", "--modified", "--added"]).unwrap();
+        assert_eq!(args.options.only(), vec!["M", "A"]);
+
+        let args = Args::try_parse_from(["branchify", "--only", "D,??", "--conflicts"]).unwrap();
+        assert_eq!(args.options.only(), vec!["D", "??", "U", "AA", "DD"]);
+    }
+
+    #[test]
+    fn test_generate_tree_timed() {
+        let paths = parse_lines(&["src/main.rs".to_string(), "README.md".to_string()]);
+        let options = Options::default();
+        let mut timings = Vec::new();
+        assert_eq!(
+            generate_tree_timed(&paths, &options, Duration::from_millis(3), &mut timings),
+            generate_tree_from_paths(&paths, &options)
+        );
+        let timings = String::from_utf8(timings).unwrap();
+        assert!(timings.starts_with("branchify: parse 3ms, build "));
+        assert!(timings.contains(", render "));
+        assert!(timings.ends_with('\n'));
+    }
+
+    /// Hands out one line per read, first checking that every line before it was written out.
+    struct IncrementalReader {
+        lines: Vec<&'static str>,
+        next: usize,
+        output: Rc<RefCell<Vec<u8>>>,
+    }
+
+    impl Read for IncrementalReader {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let written = String::from_utf8(self.output.borrow().clone()).unwrap();
+            assert_eq!(written.lines().count(), self.next);
+            let Some(line) = self.lines.get(self.next) else {
+                return Ok(0);
+            };
+            self.next += 1;
+            let line = format!("{}\n", line);
+            buf[..line.len()].copy_from_slice(line.as_bytes());
+            Ok(line.len())
+        }
+    }
+
+    struct SharedOutput(Rc<RefCell<Vec<u8>>>);
+
+    impl Write for SharedOutput {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.borrow_mut().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// Reads what the test sends it, blocking until it does.
+    struct ChannelReader(mpsc::Receiver<&'static str>);
+
+    impl Read for ChannelReader {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let Ok(chunk) = self.0.recv() else {
+                return Ok(0);
+            };
+            buf[..chunk.len()].copy_from_slice(chunk.as_bytes());
+            Ok(chunk.len())
+        }
+    }
+
+    #[test]
+    fn test_wait_for_input() {
+        let (sender, receiver) = mpsc::channel();
+        sender.send(" M src/main.rs\n").unwrap();
+        drop(sender);
+        let input = io::BufReader::new(ChannelReader(receiver));
+        let input = wait_for_input(input, Duration::from_secs(10)).unwrap();
+        assert_eq!(
+            read_lines(input, None, &mut io::sink()),
+            vec![" M src/main.rs"]
+        );
+
+        let (sender, receiver) = mpsc::channel();
+        let input = io::BufReader::new(ChannelReader(receiver));
+        assert!(wait_for_input(input, Duration::from_millis(10)).is_none());
+        // Lets the reader thread finish.
+        drop(sender);
+    }
+
+    #[test]
+    fn test_stream_paths_before_end_of_input() {
+        let output = Rc::new(RefCell::new(Vec::new()));
+        let reader = IncrementalReader {
+            lines: vec![" M src/main.rs", "?? notes.txt", "A  docs/usage.md"],
+            next: 0,
+            output: Rc::clone(&output),
+        };
+        let options = Options {
+            jsonl: true,
+            ..Default::default()
+        };
+        stream_paths(
+            io::BufReader::new(reader),
+            &mut SharedOutput(Rc::clone(&output)),
+            &mut io::sink(),
+            None,
+            None,
+            true,
+            &options,
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(output.take()).unwrap(),
+            r#"{"path":"src/main.rs","status":"M","depth":2}
+{"path":"notes.txt","status":"??","depth":1}
+{"path":"docs/usage.md","status":"A","depth":2}
+"#
+        );
+    }
+
+    #[test]
+    fn test_stream_requires_flat_or_jsonl() {
+        assert!(Args::try_parse_from(["branchify", "--stream"]).is_err());
+        assert!(Args::try_parse_from(["branchify", "--flat", "--stream"]).is_ok());
+    }
+
+    #[cfg(feature = "encoding")]
+    #[test]
+    fn test_output_encoding_values() {
+        let parse = |encoding| Args::try_parse_from(["branchify", "--output-encoding", encoding]);
+        assert!(parse("latin1").is_ok());
+        assert!(parse("ascii").is_ok());
+        assert!(parse("shift-jis").is_ok());
+        assert!(parse("utf-16").is_err());
+    }
+
+    #[test]
+    fn test_read_lines_skips_long_lines() {
+        let input = format!("src/main.rs\n{}\nREADME.md\n", "a/".repeat(100));
+        let mut warnings = Vec::new();
+        assert_eq!(
+            read_lines(input.as_bytes(), Some(64), &mut warnings),
+            vec!["src/main.rs", "README.md"]
+        );
+        assert_eq!(
+            String::from_utf8(warnings).unwrap(),
+            "branchify: skipped line 2, which is longer than 64 bytes\n"
+        );
+    }
+
+    #[test]
+    fn test_budget_writer() {
+        let paths = parse_lines(&[
+            " M src/main.rs".to_string(),
+            "A  src/lib.rs".to_string(),
+            "?? README.md".to_string(),
+        ]);
+        let output = generate_tree_from_paths(&paths, &Options::default());
+        let write = |budget| {
+            let mut out = BudgetWriter::new(Vec::new(), budget);
+            out.write_all(output.as_bytes()).unwrap();
+            out.flush().unwrap();
+            String::from_utf8(out.inner).unwrap()
+        };
+        // `├── README.md\n` and `└── src\n` are 34 bytes; `    ├── lib.rs\n` needs 21 more.
+        assert_eq!(
+            write(40),
+            "├── README.md\n└── src\n… output cut off at 40 bytes\n"
+        );
+        assert_eq!(write(output.len()), output);
+        assert_eq!(write(1 << 20), output);
+    }
+
+    #[test]
+    fn test_deduplicate_lines() {
+        let mut lines: Vec<String> = [
+            "src/main.rs",
+            "README.md",
+            "src/main.rs",
+            "src/lib.rs",
+            "README.md",
+            "src/main.rs",
+        ]
+        .into_iter()
+        .map(str::to_string)
+        .collect();
+        assert_eq!(deduplicate_lines(&mut lines), 3);
+        assert_eq!(lines, ["src/main.rs", "README.md", "src/lib.rs"]);
+        assert_eq!(
+            generate_tree_from_paths(&parse_lines(&lines), &Options::default()),
+            "├── README.md\n└── src\n    ├── lib.rs\n    └── main.rs\n"
+        );
+    }
+
+    #[test]
+    fn test_to_paths_round_trip() {
+        let input = " M src/main.rs\n?? README.md\nA  src/utils/helpers.rs\n?? docs/\n";
+        let paths = parse_lines(&input.lines().map(str::to_string).collect::<Vec<_>>());
+        let json = generate_tree_from_paths(
+            &paths,
+            &Options {
+                json: true,
+                ..Default::default()
+            },
+        );
+        assert_eq!(
+            path_list(parse_json_tree(&json).unwrap()),
+            "README.md\ndocs/\nsrc/main.rs\nsrc/utils/helpers.rs\n"
+        );
+    }
 
-    print!("{}", generate_tree_from_paths(&paths_with_status, &opts));
+    #[test]
+    fn test_or_on_empty() {
+        let paths = parse_lines(&[" M src/main.rs".to_string()]);
+        let options = Options {
+            only: vec!["A".to_string()],
+            ..Default::default()
+        };
+        let output = generate_tree_from_paths(&paths, &options);
+        assert_eq!(or_on_empty(output.clone(), None), "");
+        assert_eq!(or_on_empty(output.clone(), Some("")), "");
+        assert_eq!(or_on_empty(output, Some("No changes")), "No changes\n");
+        let output = generate_tree_from_paths(&paths, &Options::default());
+        assert_eq!(
+            or_on_empty(output, Some("No changes")),
+            "└── src\n    └── main.rs\n"
+        );
+    }
 }
@@ -0,0 +1,223 @@
+use crate::json::{self, Value};
+use colored::{Color, ColoredString, Colorize};
+use std::collections::HashMap;
+
+/// The colors the tree is drawn in. `--palette` reads them from a JSON file such as
+/// `{"statuses": {"M": "#ff8800"}, "directory": "cyan", "connector": 244}`, where the roles it
+/// leaves out keep their built-in colors.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Palette {
+    /// By status code. Statuses without one are not colored.
+    pub statuses: HashMap<String, Color>,
+    pub directory: Color,
+    /// The tree guides.
+    pub connector: Color,
+    /// Files with one of `Options::highlight_extensions`, which are bold and underlined either
+    /// way. `None` keeps their status color.
+    pub highlight: Option<Color>,
+    /// Whether the terminal shows true colors, so that `paint` writes them as such. Otherwise
+    /// `colored` decides, going by `COLORTERM`.
+    pub truecolor: bool,
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        let statuses = [
+            ("M", Color::Yellow),
+            ("A", Color::Green),
+            ("D", Color::Red),
+            ("R", Color::Cyan),
+            ("C", Color::Magenta),
+            ("U", Color::Red),
+            ("??", Color::BrightBlack),
+        ];
+        Palette {
+            statuses: statuses
+                .into_iter()
+                .map(|(status, color)| (status.to_string(), color))
+                .collect(),
+            directory: Color::Blue,
+            connector: Color::BrightBlack,
+            highlight: None,
+            truecolor: false,
+        }
+    }
+}
+
+impl Palette {
+    /// `s` in the color of `status`. Conflicts are bold as well.
+    pub fn paint(&self, s: &str, status: Option<&str>) -> ColoredString {
+        let colored = match status.and_then(|status| self.statuses.get(status)) {
+            Some(&Color::TrueColor { r, g, b })
+                if self.truecolor && colored::control::SHOULD_COLORIZE.should_colorize() =>
+            {
+                format!("\x1b[38;2;{};{};{}m{}\x1b[0m", r, g, b, s).normal()
+            }
+            Some(&color) => s.color(color),
+            None => s.normal(),
+        };
+        if status == Some("U") {
+            colored.bold()
+        } else {
+            colored
+        }
+    }
+
+    /// The background for `Options::status_bg`: the same color `paint` gives `status`.
+    pub fn background(&self, status: Option<&str>) -> Option<Color> {
+        self.statuses.get(status?).copied()
+    }
+}
+
+/// Reads a `--palette` file.
+pub fn parse(input: &str) -> Result<Palette, String> {
+    let Value::Object(members) = json::parse(input)? else {
+        return Err("expected an object".to_string());
+    };
+    let mut palette = Palette::default();
+    for (role, value) in members {
+        match (role.as_str(), value) {
+            ("statuses", Value::Object(statuses)) => {
+                for (status, value) in statuses {
+                    let color = color_value(&format!("statuses.{}", status), &value)?;
+                    palette.statuses.insert(status, color);
+                }
+            }
+            ("statuses", _) => return Err("`statuses` must be an object".to_string()),
+            ("directory", value) => palette.directory = color_value(&role, &value)?,
+            ("connector", value) => palette.connector = color_value(&role, &value)?,
+            ("highlight", value) => palette.highlight = Some(color_value(&role, &value)?),
+            _ => return Err(format!("unknown role `{}`", role)),
+        }
+    }
+    Ok(palette)
+}
+
+fn color_value(role: &str, value: &Value) -> Result<Color, String> {
+    let spec = match value {
+        Value::String(spec) => spec.clone(),
+        Value::Number(index) => index.to_string(),
+        _ => return Err(format!("`{}` must be a string or a number", role)),
+    };
+    parse_color(&spec).map_err(|err| format!("`{}`: {}", role, err))
+}
+
+/// Parses a color name such as `red` or `bright black`, a 256-color index such as `208`, or
+/// `#rrggbb`. True colors are only written as such when `Palette::truecolor` is set or
+/// `COLORTERM` is `truecolor` or `24bit`; otherwise `colored` picks the nearest basic color.
+pub fn parse_color(spec: &str) -> Result<Color, String> {
+    let invalid = || format!("invalid color `{}`", spec);
+    if let Some(hex) = spec.strip_prefix('#') {
+        if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(invalid());
+        }
+        let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).unwrap();
+        return Ok(Color::TrueColor {
+            r: channel(0),
+            g: channel(2),
+            b: channel(4),
+        });
+    }
+    if let Ok(index) = spec.parse::<u8>() {
+        return Ok(xterm_color(index));
+    }
+    spec.parse().map_err(|()| invalid())
+}
+
+/// The color of `index` in the xterm 256-color palette. `colored` has no 256-color codes, so
+/// those past the first 16 are written as the true color they stand for.
+fn xterm_color(index: u8) -> Color {
+    const BASIC: [Color; 16] = [
+        Color::Black,
+        Color::Red,
+        Color::Green,
+        Color::Yellow,
+        Color::Blue,
+        Color::Magenta,
+        Color::Cyan,
+        Color::White,
+        Color::BrightBlack,
+        Color::BrightRed,
+        Color::BrightGreen,
+        Color::BrightYellow,
+        Color::BrightBlue,
+        Color::BrightMagenta,
+        Color::BrightCyan,
+        Color::BrightWhite,
+    ];
+    match index {
+        0..=15 => BASIC[usize::from(index)],
+        16..=231 => {
+            // A 6×6×6 cube.
+            let level = |n: u8| if n == 0 { 0 } else { 55 + n * 40 };
+            let n = index - 16;
+            Color::TrueColor {
+                r: level(n / 36),
+                g: level(n / 6 % 6),
+                b: level(n % 6),
+            }
+        }
+        _ => {
+            let gray = 8 + (index - 232) * 10;
+            Color::TrueColor {
+                r: gray,
+                g: gray,
+                b: gray,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        colored::control::set_override(true);
+        let palette =
+            parse(r##"{"statuses": {"M": "#ff8800", "A": "cyan"}, "connector": 244}"##).unwrap();
+        assert_eq!(
+            palette.statuses["M"],
+            Color::TrueColor {
+                r: 255,
+                g: 136,
+                b: 0
+            }
+        );
+        assert_eq!(
+            palette.paint("main.rs", Some("A")).to_string(),
+            "\u{1b}[36mmain.rs\u{1b}[0m"
+        );
+        assert_eq!(
+            Palette {
+                truecolor: true,
+                ..palette.clone()
+            }
+            .paint("main.rs", Some("M"))
+            .to_string(),
+            "\u{1b}[38;2;255;136;0mmain.rs\u{1b}[0m"
+        );
+        assert_eq!(palette.background(Some("A")), Some(Color::Cyan));
+        assert_eq!(palette.background(None), None);
+        assert_eq!(
+            palette.connector,
+            Color::TrueColor {
+                r: 128,
+                g: 128,
+                b: 128
+            }
+        );
+        assert_eq!(palette.statuses["D"], Color::Red);
+        assert_eq!(palette.directory, Color::Blue);
+
+        assert_eq!(
+            parse(r#"{"directory": "blurple"}"#),
+            Err("`directory`: invalid color `blurple`".to_string())
+        );
+        assert_eq!(
+            parse(r##"{"statuses": {"A": "#12345"}}"##),
+            Err("`statuses.A`: invalid color `#12345`".to_string())
+        );
+    }
+}
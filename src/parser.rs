@@ -0,0 +1,776 @@
+use crate::json;
+use crate::tree_generator::{tree_from_json, tree_to_flat, DiffStat, Numstat, PathEntry};
+use std::collections::HashMap;
+
+/// Turns the lines read from stdin into path entries, detecting the input format from the
+/// first line: `git diff --numstat`, timestamped paths, `git diff --stat`,
+/// `git status --porcelain`, or a plain list of paths.
+///
+/// Paths are kept exactly as given, trailing whitespace included, because git allows file names
+/// like `"foo "`. Line endings are expected to be stripped already. Porcelain paths that git
+/// wrapped in double quotes are unquoted.
+pub fn parse_lines(lines: &[String]) -> Vec<PathEntry> {
+    let Some(first) = lines.first() else {
+        return Vec::new();
+    };
+    parse_lines_as(lines, detect_format(first))
+}
+
+/// The input format `parse_lines` reads the rest of the input as, given its first line.
+pub fn detect_format(first_line: &str) -> InputFormat {
+    if parse_numstat_line(first_line).is_some() {
+        InputFormat::Numstat
+    } else if parse_timestamp_line(first_line).is_some() {
+        InputFormat::Timestamp
+    } else if parse_diffstat_line(first_line).is_some() {
+        InputFormat::Diffstat
+    } else if is_porcelain_line(first_line) {
+        InputFormat::Porcelain
+    } else {
+        InputFormat::Plain
+    }
+}
+
+/// An input format for `parse_lines_as`, to use instead of the one `parse_lines` detects.
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum InputFormat {
+    /// `git status --porcelain`: `XY path`.
+    Porcelain,
+    /// `git diff --name-status`: `status<TAB>path`, with both paths of renames and copies.
+    NameStatus,
+    /// `git diff --numstat`: `added<TAB>deleted<TAB>path`.
+    Numstat,
+    /// `git diff --stat`: ` path | changes +++--`.
+    Diffstat,
+    /// `seconds since the epoch<TAB>path`.
+    Timestamp,
+    /// One path per line.
+    Plain,
+}
+
+/// Turns the lines read from stdin into path entries, reading them as `format`. Lines that do
+/// not fit the format are skipped.
+pub fn parse_lines_as(lines: &[String], format: InputFormat) -> Vec<PathEntry> {
+    lines
+        .iter()
+        .filter_map(|line| parse_line(line, format))
+        .collect()
+}
+
+/// Parses one line of input in `format`, or returns `None` if it does not fit.
+pub fn parse_line(line: &str, format: InputFormat) -> Option<PathEntry> {
+    match format {
+        InputFormat::Porcelain => parse_porcelain_line(line),
+        InputFormat::NameStatus => parse_name_status_line(line),
+        InputFormat::Numstat => parse_numstat_line(line),
+        InputFormat::Diffstat => parse_diffstat_line(line),
+        InputFormat::Timestamp => parse_timestamp_line(line),
+        InputFormat::Plain => Some(PathEntry::new(line, "")),
+    }
+}
+
+/// Reads a `--json` tree back into the paths it was built from, for `--to-paths`. Statuses come
+/// back too, and empty directories and directories with a status of their own keep a trailing
+/// slash.
+pub fn parse_json_tree(input: &str) -> Result<Vec<PathEntry>, String> {
+    tree_from_json(input).map(|tree| {
+        tree_to_flat(&tree)
+            .into_iter()
+            .map(|(path, status)| PathEntry::new(&path, status.as_deref().unwrap_or("")))
+            .collect()
+    })
+}
+
+/// Reads a `--status-file`: one `<path>\t<status>` per line. Lines without a tab are skipped.
+pub fn parse_status_map(contents: &str) -> HashMap<String, String> {
+    contents
+        .lines()
+        .filter_map(|line| line.split_once('\t'))
+        .map(|(path, status)| (path.to_string(), status.trim().to_string()))
+        .collect()
+}
+
+/// Reads a `--status-map`: one `<code>=<category>` per line, e.g. `changed=modified`, giving
+/// the git status each code of another tool stands for. The category may also be a git status
+/// like `M`. Blank lines and lines starting with `#` are skipped.
+pub fn parse_status_codes(contents: &str) -> Result<HashMap<String, String>, String> {
+    let mut codes = HashMap::new();
+    for (i, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (code, category) = line
+            .split_once('=')
+            .ok_or_else(|| format!("line {}: expected CODE=CATEGORY", i + 1))?;
+        let status = canonical_status(category.trim())
+            .ok_or_else(|| format!("line {}: unknown category `{}`", i + 1, category.trim()))?;
+        codes.insert(code.trim().to_string(), status.to_string());
+    }
+    Ok(codes)
+}
+
+/// The git status for a `--status-map` category.
+fn canonical_status(category: &str) -> Option<&'static str> {
+    Some(match category.to_ascii_lowercase().as_str() {
+        "modified" | "m" => "M",
+        "added" | "a" => "A",
+        "deleted" | "d" => "D",
+        "renamed" | "r" => "R",
+        "copied" | "c" => "C",
+        "conflicted" | "unmerged" | "u" => "U",
+        "typechange" | "t" => "T",
+        "untracked" | "??" => "??",
+        _ => return None,
+    })
+}
+
+/// Removes ANSI escape sequences such as `\x1b[31m` from `line`, so that colored input like
+/// `git -c color.status=always status --short` parses into plain paths.
+pub fn strip_ansi(line: &str) -> String {
+    let mut stripped = String::with_capacity(line.len());
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        if c != '\u{1b}' {
+            stripped.push(c);
+            continue;
+        }
+        // A control sequence runs until its final byte in `@`..=`~`.
+        if chars.as_str().starts_with('[') {
+            chars.next();
+            for c in chars.by_ref() {
+                if ('@'..='~').contains(&c) {
+                    break;
+                }
+            }
+        }
+    }
+    stripped
+}
+
+// Heuristic to check if the input is likely from `git status --porcelain`
+// It checks for two status characters followed by a space, e.g., "M  file.txt"
+fn is_porcelain_line(line: &str) -> bool {
+    if line.len() < 4 {
+        return false;
+    }
+    // Status part should not be empty after trim, and separator must be a space.
+    split_porcelain_line(line).is_some_and(|(status, _)| !status.trim().is_empty())
+}
+
+/// Splits `XY path` into its status and path, or returns `None` if there is no space after the
+/// two status characters.
+fn split_porcelain_line(line: &str) -> Option<(&str, &str)> {
+    if line.get(2..3)? != " " {
+        return None;
+    }
+    Some((line.get(..2)?, line.get(3..)?))
+}
+
+/// Where and why a line of input did not fit its format, for `--strict-input`.
+#[derive(Debug, PartialEq)]
+pub struct Diagnostic {
+    /// Counted from 1.
+    pub line: usize,
+    /// Counted from 1, in characters.
+    pub column: usize,
+    /// What the parser was looking for there, e.g. `a tab`.
+    pub expected: &'static str,
+    pub text: String,
+}
+
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "line {}, column {}: expected {}: `{}`",
+            self.line, self.column, self.expected, self.text
+        )
+    }
+}
+
+impl Diagnostic {
+    /// `{"line":…,"column":…,"expected":…,"text":…}`, for `--error-format json`.
+    pub fn to_json(&self) -> String {
+        let mut out = format!(
+            "{{\"line\":{},\"column\":{},\"expected\":",
+            self.line, self.column
+        );
+        json::write_string(&mut out, self.expected);
+        out.push_str(",\"text\":");
+        json::write_string(&mut out, &self.text);
+        out.push('}');
+        out
+    }
+}
+
+/// Checks that every line fits `format`, instead of skipping those that do not like
+/// `parse_lines_as` does. The diagnostic points at the first one that does not.
+pub fn validate_lines(lines: &[String], format: InputFormat) -> Result<(), Diagnostic> {
+    for (i, line) in lines.iter().enumerate() {
+        if let Err((offset, expected)) = check_line(line, format) {
+            return Err(Diagnostic {
+                line: i + 1,
+                column: line[..offset].chars().count() + 1,
+                expected,
+                text: line.clone(),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// The byte offset in `line` where it stops fitting `format`, and what should have been there.
+fn check_line(line: &str, format: InputFormat) -> Result<(), (usize, &'static str)> {
+    let tab = |from: usize| match line[from..].find('\t') {
+        Some(i) => Ok(from + i),
+        None => Err((line.len(), "a tab")),
+    };
+    let path = |from: usize| match line[from..].is_empty() {
+        true => Err((from, "a path")),
+        false => Ok(()),
+    };
+    match format {
+        InputFormat::Porcelain => {
+            let mut chars = line.char_indices();
+            for _ in 0..2 {
+                match chars.next() {
+                    Some((_, c)) if " MTADRCU?!".contains(c) => {}
+                    Some((i, _)) => return Err((i, "a status letter")),
+                    None => return Err((line.len(), "a status letter")),
+                }
+            }
+            if line[..2].trim().is_empty() {
+                return Err((0, "a status letter"));
+            }
+            if line.get(2..3) != Some(" ") {
+                return Err((2, "a space"));
+            }
+            path(3)?;
+            if line.starts_with(['R', 'C']) && rename_target(&line[3..]).is_none() {
+                return Err((3, "`old -> new`"));
+            }
+            Ok(())
+        }
+        InputFormat::NameStatus => {
+            let end = tab(0)?;
+            if line[..end]
+                .trim_end_matches(|c: char| c.is_ascii_digit())
+                .is_empty()
+            {
+                return Err((0, "a status letter"));
+            }
+            path(end + 1)
+        }
+        InputFormat::Numstat => {
+            let mut start = 0;
+            for _ in 0..2 {
+                let end = line[start..]
+                    .find(|c: char| !c.is_ascii_digit() && c != '-')
+                    .map_or(line.len(), |len| start + len);
+                if parse_numstat_count(&line[start..end]).is_none() {
+                    return Err((start, "a line count or `-`"));
+                }
+                if !line[end..].starts_with('\t') {
+                    return Err((end, "a tab"));
+                }
+                start = end + 1;
+            }
+            path(start)
+        }
+        InputFormat::Diffstat => {
+            // The stat ends with a summary like ` 3 files changed, 10 insertions(+)`.
+            let is_summary = line
+                .trim_start()
+                .split_once(' ')
+                .is_some_and(|(count, rest)| {
+                    count.parse::<u64>().is_ok() && rest.starts_with("file")
+                });
+            if is_summary || parse_diffstat_line(line).is_some() {
+                return Ok(());
+            }
+            match line.rfind(" | ") {
+                None => Err((line.len(), "` | `")),
+                Some(0) => Err((0, "a path")),
+                Some(i) => Err((i + 3, "a number of changes and a bar")),
+            }
+        }
+        InputFormat::Timestamp => {
+            let end = tab(0)?;
+            if line[..end].parse::<u64>().is_err() {
+                return Err((0, "seconds since the epoch"));
+            }
+            path(end + 1)
+        }
+        InputFormat::Plain => Ok(()),
+    }
+}
+
+fn parse_porcelain_line(line: &str) -> Option<PathEntry> {
+    if line.len() < 4 {
+        return None;
+    }
+    // A line without the separator is not a status line, so take all of it as the path.
+    let Some((status_str, path_str)) = split_porcelain_line(line) else {
+        return Some(PathEntry::new(line, ""));
+    };
+
+    let status = status_str.trim();
+    if status.is_empty() {
+        return None;
+    }
+
+    // For renames "R  old -> new" and copies "C  src -> dst", we want to display the new path
+    if status.starts_with('R') || status.starts_with('C') {
+        if let Some(new_path) = rename_target(path_str) {
+            return Some(PathEntry::new(&unquote_path(new_path), status));
+        }
+    }
+    Some(PathEntry::new(&unquote_path(path_str), status))
+}
+
+/// The new path of a rename or copy `old -> new`, still quoted if it was. git quotes a path
+/// with spaces here, so a quoted side may contain ` -> ` itself and the quotes decide where to
+/// split. Without them the last ` -> ` does.
+fn rename_target(paths: &str) -> Option<&str> {
+    if let Some(end) = quoted_len(paths) {
+        return paths[end..].strip_prefix(" -> ");
+    }
+    let separator = if paths.ends_with('"') {
+        paths.rfind(" -> \"").or_else(|| paths.rfind(" -> "))
+    } else {
+        paths.rfind(" -> ")
+    }?;
+    Some(&paths[separator + 4..])
+}
+
+/// The length, quotes included, of the quoted path `s` starts with.
+fn quoted_len(s: &str) -> Option<usize> {
+    let rest = s.strip_prefix('"')?;
+    let mut escaped = false;
+    for (i, c) in rest.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '"' => return Some(i + 2),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Undoes the C-style quoting git applies to unusual paths, e.g. `"tab\there"` or
+/// `"caf\303\251"`. Unquoted paths, including any leading or trailing spaces, are returned
+/// unchanged.
+fn unquote_path(path: &str) -> String {
+    let Some(inner) = path
+        .strip_prefix('"')
+        .and_then(|rest| rest.strip_suffix('"'))
+    else {
+        return path.to_string();
+    };
+    let mut bytes = Vec::new();
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            let mut buf = [0; 4];
+            bytes.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+            continue;
+        }
+        match chars.next() {
+            Some('a') => bytes.push(0x07),
+            Some('b') => bytes.push(0x08),
+            Some('t') => bytes.push(b'\t'),
+            Some('n') => bytes.push(b'\n'),
+            Some('v') => bytes.push(0x0b),
+            Some('f') => bytes.push(0x0c),
+            Some('r') => bytes.push(b'\r'),
+            Some(digit @ '0'..='3') => {
+                // Three octal digits for one byte of a multi-byte character.
+                let octal: String = std::iter::once(digit)
+                    .chain(chars.by_ref().take(2))
+                    .collect();
+                match u8::from_str_radix(&octal, 8) {
+                    Ok(byte) => bytes.push(byte),
+                    Err(_) => return path.to_string(),
+                }
+            }
+            Some(other) => {
+                let mut buf = [0; 4];
+                bytes.extend_from_slice(other.encode_utf8(&mut buf).as_bytes());
+            }
+            None => return path.to_string(),
+        }
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+/// Parses `<status>\t<path>`, or `<status><score>\t<old>\t<new>` for renames and copies, of
+/// which the new path is kept and the similarity score dropped.
+fn parse_name_status_line(line: &str) -> Option<PathEntry> {
+    let (status, paths) = line.split_once('\t')?;
+    let status = status.trim_end_matches(|c: char| c.is_ascii_digit());
+    if status.is_empty() || paths.is_empty() {
+        return None;
+    }
+    let path = match paths.split_once('\t') {
+        Some((_, new_path)) if status.starts_with('R') || status.starts_with('C') => new_path,
+        _ => paths,
+    };
+    Some(PathEntry::new(&unquote_path(path), status))
+}
+
+/// Parses `<added>\t<deleted>\t<path>`, where binary files report `-` for both counts.
+fn parse_numstat_line(line: &str) -> Option<PathEntry> {
+    let mut fields = line.splitn(3, '\t');
+    let added = parse_numstat_count(fields.next()?)?;
+    let deleted = parse_numstat_count(fields.next()?)?;
+    let path = fields.next()?;
+    Some(PathEntry {
+        numstat: Some(Numstat { added, deleted }),
+        ..PathEntry::new(path, "")
+    })
+}
+
+/// Parses ` <path> | <changes> <bar>` or, for binary files, ` <path> | Bin <old> -> <new> bytes`.
+/// The trailing `N files changed` summary does not match and is skipped.
+fn parse_diffstat_line(line: &str) -> Option<PathEntry> {
+    let (path, stat) = line.rsplit_once(" | ")?;
+    let path = path.trim();
+    let stat = stat.trim();
+    if path.is_empty() {
+        return None;
+    }
+    let diffstat = if stat.starts_with("Bin") {
+        DiffStat::default()
+    } else {
+        let (changes, bar) = stat.split_once(' ').unwrap_or((stat, ""));
+        if !bar.chars().all(|c| c == '+' || c == '-') {
+            return None;
+        }
+        DiffStat {
+            changes: Some(changes.parse().ok()?),
+            bar: bar.to_string(),
+        }
+    };
+    Some(PathEntry {
+        diffstat: Some(diffstat),
+        ..PathEntry::new(path, "")
+    })
+}
+
+/// Parses `<seconds since the epoch>\t<path>`, e.g. from
+/// `git log -1 --format="%at%x09$path"` run per file.
+fn parse_timestamp_line(line: &str) -> Option<PathEntry> {
+    let (timestamp, path) = line.split_once('\t')?;
+    if path.is_empty() {
+        return None;
+    }
+    Some(PathEntry {
+        timestamp: Some(timestamp.parse().ok()?),
+        ..PathEntry::new(path, "")
+    })
+}
+
+fn parse_numstat_count(field: &str) -> Option<Option<u64>> {
+    if field == "-" {
+        Some(None)
+    } else {
+        field.parse().ok().map(Some)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tree_generator::{generate_tree_from_paths, Options};
+
+    fn lines(input: &str) -> Vec<String> {
+        input.lines().map(str::to_string).collect()
+    }
+
+    #[test]
+    fn test_parse_numstat() {
+        let entries = parse_lines(&lines("12\t3\tsrc/main.rs\n-\t-\tlogo.png\n"));
+        assert_eq!(
+            entries,
+            vec![
+                PathEntry {
+                    numstat: Some(Numstat {
+                        added: Some(12),
+                        deleted: Some(3),
+                    }),
+                    ..PathEntry::new("src/main.rs", "")
+                },
+                PathEntry {
+                    numstat: Some(Numstat {
+                        added: None,
+                        deleted: None,
+                    }),
+                    ..PathEntry::new("logo.png", "")
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_generate_tree_from_numstat() {
+        let entries = parse_lines(&lines(
+            "12\t3\tsrc/main.rs\n4\t0\tsrc/tree_generator.rs\n-\t-\tlogo.png\n1\t1\tREADME.md\n",
+        ));
+        assert_eq!(
+            generate_tree_from_paths(&entries, &Options::default()),
+            r#"├── README.md (+1 -1)
+├── logo.png (binary)
+└── src (+16 -3)
+    ├── main.rs (+12 -3)
+    └── tree_generator.rs (+4 -0)
+"#
+        );
+    }
+
+    #[test]
+    fn test_parse_porcelain_copy() {
+        let entries = parse_lines(&lines("C  a.txt -> b.txt\n"));
+        assert_eq!(entries, vec![PathEntry::new("b.txt", "C")]);
+
+        colored::control::set_override(true);
+        let options = &Options {
+            color: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            generate_tree_from_paths(&entries, options),
+            "\u{1b}[90m\u{1b}[0m\u{1b}[90m└── \u{1b}[0m\u{1b}[35mb.txt\u{1b}[0m\n"
+        );
+    }
+
+    #[test]
+    fn test_parse_diffstat() {
+        let entries = parse_lines(&lines(
+            " src/main.rs | 12 +++---\n README.md   |  1 +\n 2 files changed, 7 insertions(+), 6 deletions(-)\n",
+        ));
+        assert_eq!(
+            entries,
+            vec![
+                PathEntry {
+                    diffstat: Some(DiffStat {
+                        changes: Some(12),
+                        bar: "+++---".to_string(),
+                    }),
+                    ..PathEntry::new("src/main.rs", "")
+                },
+                PathEntry {
+                    diffstat: Some(DiffStat {
+                        changes: Some(1),
+                        bar: "+".to_string(),
+                    }),
+                    ..PathEntry::new("README.md", "")
+                },
+            ]
+        );
+        assert_eq!(
+            generate_tree_from_paths(&entries, &Options::default()),
+            r#"├── README.md (1 +)
+└── src
+    └── main.rs (12 +++---)
+"#
+        );
+    }
+
+    #[test]
+    fn test_parse_timestamps() {
+        let entries = parse_lines(&lines("1700000000\tsrc/main.rs\n1600000000\tREADME.md\n"));
+        assert_eq!(
+            entries,
+            vec![
+                PathEntry {
+                    timestamp: Some(1700000000),
+                    ..PathEntry::new("src/main.rs", "")
+                },
+                PathEntry {
+                    timestamp: Some(1600000000),
+                    ..PathEntry::new("README.md", "")
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_porcelain_line_without_separator() {
+        let entries = parse_lines(&lines(" M src/main.rs\nM-file.txt\n"));
+        assert_eq!(
+            entries,
+            vec![
+                PathEntry::new("src/main.rs", "M"),
+                PathEntry::new("M-file.txt", ""),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_paths_with_trailing_spaces() {
+        let expected = vec![PathEntry::new("foo ", ""), PathEntry::new("dir /bar", "")];
+        assert_eq!(parse_lines(&lines("foo \ndir /bar\n")), expected);
+
+        let entries = parse_lines(&lines("?? foo \nR  old -> dir /bar\n"));
+        assert_eq!(
+            entries,
+            vec![
+                PathEntry::new("foo ", "??"),
+                PathEntry::new("dir /bar", "R")
+            ]
+        );
+        assert_eq!(
+            generate_tree_from_paths(&entries, &Options::default()),
+            "├── dir \n│   └── bar\n└── foo \n"
+        );
+    }
+
+    #[test]
+    fn test_parse_quoted_porcelain_paths() {
+        let entries = parse_lines(&lines(
+            "?? \"say \\\"hi\\\" \"\n M \"caf\\303\\251/tab\\there\"\n",
+        ));
+        assert_eq!(
+            entries,
+            vec![
+                PathEntry::new("say \"hi\" ", "??"),
+                PathEntry::new("café/tab\there", "M"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_rename_with_arrow_in_path() {
+        let entries = parse_lines(&lines(
+            "R  \"old -> name.txt\" -> new.txt\nR  \"a \\\" -> b\" -> \"c -> d\"\nR  x -> y -> z\n",
+        ));
+        assert_eq!(
+            entries,
+            vec![
+                PathEntry::new("new.txt", "R"),
+                PathEntry::new("c -> d", "R"),
+                PathEntry::new("z", "R"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_strip_ansi() {
+        let input = lines(
+            " \u{1b}[31mM\u{1b}[m \u{1b}[31msrc/main.rs\u{1b}[0m\n\u{1b}[1;32mA\u{1b}[0m  lib.rs\n",
+        );
+        let entries = parse_lines(
+            &input
+                .iter()
+                .map(|line| strip_ansi(line))
+                .collect::<Vec<_>>(),
+        );
+        assert_eq!(
+            entries,
+            vec![
+                PathEntry::new("src/main.rs", "M"),
+                PathEntry::new("lib.rs", "A")
+            ]
+        );
+        assert_eq!(
+            generate_tree_from_paths(&entries, &Options::default()),
+            "├── lib.rs\n└── src\n    └── main.rs\n"
+        );
+    }
+
+    #[test]
+    fn test_parse_status_map() {
+        let statuses = parse_status_map("src/main.rs\tM\nREADME.md\t??\nno tab here\n");
+        assert_eq!(
+            statuses,
+            HashMap::from([
+                ("src/main.rs".to_string(), "M".to_string()),
+                ("README.md".to_string(), "??".to_string()),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_parse_status_codes() {
+        let codes = parse_status_codes("# svn\nchanged = modified\nnew=A\n\n?=untracked\n");
+        assert_eq!(
+            codes,
+            Ok(HashMap::from([
+                ("changed".to_string(), "M".to_string()),
+                ("new".to_string(), "A".to_string()),
+                ("?".to_string(), "??".to_string()),
+            ]))
+        );
+        assert_eq!(
+            parse_status_codes("gone=vanished"),
+            Err("line 1: unknown category `vanished`".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_lines_as_name_status() {
+        let input = lines("M\tsrc/main.rs\nR087\tlib.rs\tsrc/lib.rs\nA\tREADME.md\n");
+        // Detection takes the tab-separated statuses for part of plain paths.
+        assert_eq!(parse_lines(&input)[0], PathEntry::new("M\tsrc/main.rs", ""));
+        assert_eq!(
+            parse_lines_as(&input, InputFormat::NameStatus),
+            vec![
+                PathEntry::new("src/main.rs", "M"),
+                PathEntry::new("src/lib.rs", "R"),
+                PathEntry::new("README.md", "A"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_lines_as_plain() {
+        let input = lines("12\t3\tsrc/main.rs\n");
+        assert_eq!(
+            parse_lines_as(&input, InputFormat::Plain),
+            vec![PathEntry::new("12\t3\tsrc/main.rs", "")]
+        );
+    }
+
+    #[test]
+    fn test_validate_lines() {
+        let valid = lines(" M src/main.rs\nA  src/lib.rs\nR  old.rs -> new.rs\n?? build/\n");
+        assert_eq!(validate_lines(&valid, InputFormat::Porcelain), Ok(()));
+
+        let diagnostic = |input: &str, format| {
+            validate_lines(&lines(input), format)
+                .unwrap_err()
+                .to_string()
+        };
+        assert_eq!(
+            diagnostic(
+                " M src/main.rs\nMM\tsrc/lib.rs\n?? build/\n",
+                InputFormat::Porcelain
+            ),
+            "line 2, column 3: expected a space: `MM\tsrc/lib.rs`"
+        );
+        assert_eq!(
+            diagnostic(" M a.rs\n?? b.rs\nXY c.rs\n", InputFormat::Porcelain),
+            "line 3, column 1: expected a status letter: `XY c.rs`"
+        );
+        assert_eq!(
+            diagnostic("R  old.rs\n", InputFormat::Porcelain),
+            "line 1, column 4: expected `old -> new`: `R  old.rs`"
+        );
+        assert_eq!(
+            diagnostic("1\t2\ta.rs\n10\t4 b.rs\n", InputFormat::Numstat),
+            "line 2, column 5: expected a tab: `10\t4 b.rs`"
+        );
+        assert_eq!(
+            diagnostic("1\tx\ta.rs\n", InputFormat::Numstat),
+            "line 1, column 3: expected a line count or `-`: `1\tx\ta.rs`"
+        );
+        assert_eq!(
+            validate_lines(&lines("a.rs\n"), InputFormat::NameStatus)
+                .unwrap_err()
+                .to_json(),
+            r#"{"line":1,"column":5,"expected":"a tab","text":"a.rs"}"#
+        );
+    }
+}
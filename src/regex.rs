@@ -0,0 +1,298 @@
+use std::str::FromStr;
+
+/// A regular expression for options like `--collapse-by-regex`, parsed by `Regex::from_str`.
+///
+/// Supports literals, `.`, classes like `[a-z_]` and `[^/]`, the escapes `\d`, `\w` and `\s`,
+/// groups, `|`, the anchors `^` and `$`, and the repetitions `*`, `+`, `?` and `{n,m}`.
+/// Matching backtracks, so it is meant for short patterns over names, not for large inputs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Regex {
+    alternatives: Vec<Vec<Node>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Char(char),
+    Any,
+    Class {
+        ranges: Vec<(char, char)>,
+        negated: bool,
+    },
+    Start,
+    End,
+    Group(Vec<Vec<Node>>),
+    Repeat {
+        node: Box<Node>,
+        min: usize,
+        max: Option<usize>,
+    },
+}
+
+impl FromStr for Regex {
+    type Err = String;
+
+    fn from_str(pattern: &str) -> Result<Self, Self::Err> {
+        let chars: Vec<char> = pattern.chars().collect();
+        let mut parser = Parser {
+            chars: &chars,
+            pos: 0,
+        };
+        let alternatives = parser.parse_alternatives()?;
+        if parser.pos < chars.len() {
+            return Err("unmatched `)`".to_string());
+        }
+        Ok(Regex { alternatives })
+    }
+}
+
+impl Regex {
+    /// Whether the pattern matches anywhere in `s`.
+    pub fn is_match(&self, s: &str) -> bool {
+        let input: Vec<char> = s.chars().collect();
+        (0..=input.len())
+            .any(|start| match_alternatives(&self.alternatives, &input, start, &mut |_| true))
+    }
+}
+
+struct Parser<'a> {
+    chars: &'a [char],
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += 1;
+        Some(c)
+    }
+
+    fn parse_alternatives(&mut self) -> Result<Vec<Vec<Node>>, String> {
+        let mut alternatives = vec![self.parse_sequence()?];
+        while self.peek() == Some('|') {
+            self.pos += 1;
+            alternatives.push(self.parse_sequence()?);
+        }
+        Ok(alternatives)
+    }
+
+    fn parse_sequence(&mut self) -> Result<Vec<Node>, String> {
+        let mut sequence = Vec::new();
+        while let Some(c) = self.peek() {
+            if c == '|' || c == ')' {
+                break;
+            }
+            let atom = self.parse_atom()?;
+            sequence.push(self.parse_repetition(atom)?);
+        }
+        Ok(sequence)
+    }
+
+    fn parse_atom(&mut self) -> Result<Node, String> {
+        match self.next().unwrap() {
+            '(' => {
+                // Groups never capture, so `(?:…)` is the same as `(…)`.
+                if self.chars[self.pos..].starts_with(&['?', ':']) {
+                    self.pos += 2;
+                }
+                let alternatives = self.parse_alternatives()?;
+                if self.next() != Some(')') {
+                    return Err("unclosed `(`".to_string());
+                }
+                Ok(Node::Group(alternatives))
+            }
+            '[' => self.parse_class(),
+            '.' => Ok(Node::Any),
+            '^' => Ok(Node::Start),
+            '$' => Ok(Node::End),
+            '\\' => self.parse_escape(),
+            c @ ('*' | '+' | '?') => Err(format!("nothing to repeat before `{}`", c)),
+            c => Ok(Node::Char(c)),
+        }
+    }
+
+    fn parse_escape(&mut self) -> Result<Node, String> {
+        let class = |ranges: &[(char, char)]| Node::Class {
+            ranges: ranges.to_vec(),
+            negated: false,
+        };
+        match self.next() {
+            Some('d') => Ok(class(&[('0', '9')])),
+            Some('w') => Ok(class(&[('a', 'z'), ('A', 'Z'), ('0', '9'), ('_', '_')])),
+            Some('s') => Ok(class(&[(' ', ' '), ('\t', '\r')])),
+            Some(c) => Ok(Node::Char(c)),
+            None => Err("trailing `\\`".to_string()),
+        }
+    }
+
+    fn parse_class(&mut self) -> Result<Node, String> {
+        let negated = self.peek() == Some('^');
+        if negated {
+            self.pos += 1;
+        }
+        let mut ranges = Vec::new();
+        loop {
+            let start = match self.next() {
+                None => return Err("unclosed `[`".to_string()),
+                // A `]` right at the start is a literal one.
+                Some(']') if !ranges.is_empty() => break,
+                Some('\\') => self.next().ok_or("trailing `\\`")?,
+                Some(c) => c,
+            };
+            let end = if self.peek() == Some('-') && self.chars.get(self.pos + 1) != Some(&']') {
+                self.pos += 1;
+                self.next().ok_or("unclosed `[`")?
+            } else {
+                start
+            };
+            ranges.push((start, end));
+        }
+        Ok(Node::Class { ranges, negated })
+    }
+
+    fn parse_repetition(&mut self, node: Node) -> Result<Node, String> {
+        let (min, max) = match self.peek() {
+            Some('*') => (0, None),
+            Some('+') => (1, None),
+            Some('?') => (0, Some(1)),
+            Some('{') => return self.parse_counted_repetition(node),
+            _ => return Ok(node),
+        };
+        self.pos += 1;
+        Ok(Node::Repeat {
+            node: Box::new(node),
+            min,
+            max,
+        })
+    }
+
+    /// Reads `{n}`, `{n,}` or `{n,m}`. A `{` that does not start one of those is a literal.
+    fn parse_counted_repetition(&mut self, node: Node) -> Result<Node, String> {
+        let rest: String = self.chars[self.pos + 1..].iter().collect();
+        let Some(end) = rest.find('}') else {
+            return Ok(node);
+        };
+        let bounds = &rest[..end];
+        let parse = |s: &str| s.parse::<usize>().ok();
+        let (min, max) = match bounds.split_once(',') {
+            None => match parse(bounds) {
+                Some(n) => (n, Some(n)),
+                None => return Ok(node),
+            },
+            Some((min, "")) => match parse(min) {
+                Some(min) => (min, None),
+                None => return Ok(node),
+            },
+            Some((min, max)) => match (parse(min), parse(max)) {
+                (Some(min), Some(max)) if min <= max => (min, Some(max)),
+                (Some(_), Some(_)) => return Err(format!("bad repetition `{{{}}}`", bounds)),
+                _ => return Ok(node),
+            },
+        };
+        self.pos += 1 + rest[..=end].chars().count();
+        Ok(Node::Repeat {
+            node: Box::new(node),
+            min,
+            max,
+        })
+    }
+}
+
+type Continuation<'a> = dyn FnMut(usize) -> bool + 'a;
+
+fn match_alternatives(
+    alternatives: &[Vec<Node>],
+    input: &[char],
+    pos: usize,
+    k: &mut Continuation,
+) -> bool {
+    alternatives
+        .iter()
+        .any(|sequence| match_sequence(sequence, input, pos, k))
+}
+
+fn match_sequence(sequence: &[Node], input: &[char], pos: usize, k: &mut Continuation) -> bool {
+    match sequence.split_first() {
+        None => k(pos),
+        Some((node, rest)) => match_node(node, input, pos, &mut |next| {
+            match_sequence(rest, input, next, k)
+        }),
+    }
+}
+
+fn match_node(node: &Node, input: &[char], pos: usize, k: &mut Continuation) -> bool {
+    match node {
+        Node::Char(c) => input.get(pos) == Some(c) && k(pos + 1),
+        Node::Any => pos < input.len() && k(pos + 1),
+        Node::Class { ranges, negated } => {
+            input.get(pos).is_some_and(|c| {
+                ranges.iter().any(|(start, end)| (start..=end).contains(&c)) != *negated
+            }) && k(pos + 1)
+        }
+        Node::Start => pos == 0 && k(pos),
+        Node::End => pos == input.len() && k(pos),
+        Node::Group(alternatives) => match_alternatives(alternatives, input, pos, k),
+        Node::Repeat { node, min, max } => match_repeat(node, *min, *max, 0, input, pos, k),
+    }
+}
+
+/// Matches `node` as many times as it can, giving back one repetition at a time until the rest
+/// of the pattern matches too.
+fn match_repeat(
+    node: &Node,
+    min: usize,
+    max: Option<usize>,
+    count: usize,
+    input: &[char],
+    pos: usize,
+    k: &mut Continuation,
+) -> bool {
+    if max.is_none_or(|max| count < max) {
+        // An empty repetition past `min` could go on forever without getting anywhere.
+        let matched = match_node(node, input, pos, &mut |next| {
+            (next != pos || count < min) && match_repeat(node, min, max, count + 1, input, next, k)
+        });
+        if matched {
+            return true;
+        }
+    }
+    count >= min && k(pos)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn is_match(pattern: &str, s: &str) -> bool {
+        pattern.parse::<Regex>().unwrap().is_match(s)
+    }
+
+    #[test]
+    fn test_is_match() {
+        assert!(is_match("node_modules|target", "web/node_modules"));
+        assert!(is_match("^target$", "target"));
+        assert!(!is_match("^target$", "targets"));
+        assert!(is_match(r"^\.?cache[0-9]*$", ".cache12"));
+        assert!(is_match("^(ab)+c$", "ababc"));
+        assert!(!is_match("^(ab)+c$", "abac"));
+        assert!(is_match("^[^/]+/dist$", "app/dist"));
+        assert!(!is_match("^[^/]+/dist$", "a/b/dist"));
+        assert!(is_match(r"^v\d{1,2}$", "v12"));
+        assert!(!is_match(r"^v\d{1,2}$", "v123"));
+        assert!(is_match("^(a*)*$", "aaa"));
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!("(ab".parse::<Regex>(), Err("unclosed `(`".to_string()));
+        assert_eq!("ab)".parse::<Regex>(), Err("unmatched `)`".to_string()));
+        assert_eq!("[ab".parse::<Regex>(), Err("unclosed `[`".to_string()));
+        assert_eq!(
+            "*a".parse::<Regex>(),
+            Err("nothing to repeat before `*`".to_string())
+        );
+    }
+}
@@ -0,0 +1,138 @@
+use std::fmt::Write;
+use std::str::FromStr;
+
+/// A per-line output format such as `{status}\t{path}`, parsed by `Template::from_str`.
+///
+/// `{path}`, `{name}`, `{status}` and `{depth}` are replaced for each entry. `\t`, `\n` and `\\`
+/// are the usual escapes, and `{{`/`}}` stand for literal braces.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Template {
+    tokens: Vec<Token>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Literal(String),
+    Placeholder(Field),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Field {
+    Path,
+    Name,
+    Status,
+    Depth,
+}
+
+/// What a template can refer to for one entry.
+pub struct Fields<'a> {
+    pub path: &'a str,
+    pub name: &'a str,
+    pub status: Option<&'a str>,
+    /// 1 for top-level entries.
+    pub depth: usize,
+}
+
+impl FromStr for Template {
+    type Err = String;
+
+    fn from_str(template: &str) -> Result<Self, Self::Err> {
+        let mut tokens = Vec::new();
+        let mut literal = String::new();
+        let mut chars = template.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => literal.push(match chars.next() {
+                    Some('t') => '\t',
+                    Some('n') => '\n',
+                    Some('\\') => '\\',
+                    Some(other) => return Err(format!("unknown escape `\\{}`", other)),
+                    None => return Err("trailing `\\`".to_string()),
+                }),
+                '{' if chars.as_str().starts_with('{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.as_str().starts_with('}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '{' => {
+                    let rest = chars.as_str();
+                    let end = rest.find('}').ok_or("unclosed `{`")?;
+                    let field = match &rest[..end] {
+                        "path" => Field::Path,
+                        "name" => Field::Name,
+                        "status" => Field::Status,
+                        "depth" => Field::Depth,
+                        other => return Err(format!("unknown placeholder `{{{}}}`", other)),
+                    };
+                    chars = rest[end + 1..].chars();
+                    if !literal.is_empty() {
+                        tokens.push(Token::Literal(std::mem::take(&mut literal)));
+                    }
+                    tokens.push(Token::Placeholder(field));
+                }
+                '}' => return Err("unmatched `}`".to_string()),
+                c => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            tokens.push(Token::Literal(literal));
+        }
+        Ok(Template { tokens })
+    }
+}
+
+impl Template {
+    /// Appends the template filled in with `fields` to `out`.
+    pub fn render(&self, out: &mut String, fields: &Fields) {
+        for token in &self.tokens {
+            match token {
+                Token::Literal(s) => out.push_str(s),
+                Token::Placeholder(Field::Path) => out.push_str(fields.path),
+                Token::Placeholder(Field::Name) => out.push_str(fields.name),
+                Token::Placeholder(Field::Status) => out.push_str(fields.status.unwrap_or("")),
+                Token::Placeholder(Field::Depth) => write!(out, "{}", fields.depth).unwrap(),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_template_errors() {
+        assert_eq!(
+            "{path} {size}".parse::<Template>(),
+            Err("unknown placeholder `{size}`".to_string())
+        );
+        assert_eq!("{path".parse::<Template>(), Err("unclosed `{`".to_string()));
+        assert_eq!(
+            "a\\x".parse::<Template>(),
+            Err("unknown escape `\\x`".to_string())
+        );
+    }
+
+    #[test]
+    fn test_render_template() {
+        let fields = Fields {
+            path: "src/main.rs",
+            name: "main.rs",
+            status: Some("M"),
+            depth: 2,
+        };
+        let render = |template: &str| {
+            let mut out = String::new();
+            template
+                .parse::<Template>()
+                .unwrap()
+                .render(&mut out, &fields);
+            out
+        };
+        assert_eq!(render("{status}\\t{path}"), "M\tsrc/main.rs");
+        assert_eq!(render("{{{depth}}} {name}"), "{2} main.rs");
+    }
+}
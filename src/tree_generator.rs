@@ -1,409 +1,5082 @@
+use crate::collate;
+use crate::html;
+use crate::json;
+use crate::ordered_map::{self, OrderedMap};
+use crate::palette::Palette;
+use crate::regex::Regex;
+use crate::template::{Fields, Template};
+use crate::unicode::{self, NormalizationForm};
 use colored::*;
-use std::collections::BTreeMap;
-use std::fmt::Write;
+use std::cell::OnceCell;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::io::{self, Write};
 use std::path::Path;
+use std::rc::Rc;
 
-type Tree = BTreeMap<String, Node>;
+/// One level of the tree, keyed by entry name. Names are single path components, except after
+/// `trim_common_prefix`/`min_depth` rewrote the top level, where they may contain `/`.
+///
+/// Entries are kept in the order their paths first appeared in the input; `sorted_children`
+/// decides the order they are shown in.
+pub type Tree = OrderedMap<String, Node>;
 
-#[derive(Debug, PartialEq)]
-struct Node {
-    status: Option<String>,
-    children: Option<Tree>,
+/// A file or a directory. Directories have `Some(children)`, possibly empty, and files have
+/// `None`; `numstat`, `diffstat` and `timestamp` are only set on files.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Node {
+    pub status: Option<String>,
+    pub numstat: Option<Numstat>,
+    pub diffstat: Option<DiffStat>,
+    /// When the file last changed, in seconds since the Unix epoch.
+    pub timestamp: Option<u64>,
+    pub children: Option<Tree>,
+    /// Whether the directory was listed itself, like git's `?? build/`, rather than only as
+    /// the parent of other paths. Listed directories are kept when they end up empty.
+    pub listed: bool,
 }
 
 impl Node {
-    fn new_file(status: Option<String>) -> Self {
+    pub fn new_file(status: Option<String>, numstat: Option<Numstat>) -> Self {
         Node {
             status,
+            numstat,
+            diffstat: None,
+            timestamp: None,
             children: None,
+            listed: false,
         }
     }
 
-    fn new_directory() -> Self {
+    pub fn new_directory(status: Option<String>) -> Self {
         Node {
-            status: None,
-            children: Some(BTreeMap::new()),
+            status,
+            numstat: None,
+            diffstat: None,
+            timestamp: None,
+            children: Some(Tree::new()),
+            listed: false,
+        }
+    }
+}
+
+/// Line counts from `git diff --numstat`. Binary files report `-` for both, stored as `None`.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct Numstat {
+    pub added: Option<u64>,
+    pub deleted: Option<u64>,
+}
+
+/// A file's line from `git diff --stat`: the number of changed lines and its `+`/`-` bar.
+/// Binary files have no count.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct DiffStat {
+    pub changes: Option<u64>,
+    pub bar: String,
+}
+
+/// A single input path along with everything the parser could extract for it.
+#[derive(Debug, Default, PartialEq)]
+pub struct PathEntry {
+    pub path: String,
+    pub status: String,
+    pub numstat: Option<Numstat>,
+    pub diffstat: Option<DiffStat>,
+    pub timestamp: Option<u64>,
+}
+
+impl PathEntry {
+    pub fn new(path: &str, status: &str) -> Self {
+        PathEntry {
+            path: path.to_string(),
+            status: status.to_string(),
+            numstat: None,
+            diffstat: None,
+            timestamp: None,
         }
     }
 }
 
 #[derive(Debug, PartialEq)]
 enum LineEntry {
-    File(String, Option<String>),
-    Directory(String),
+    File {
+        name: String,
+        status: Option<String>,
+        current: bool,
+        timestamp: Option<u64>,
+    },
+    Submodule {
+        name: String,
+        status: Option<String>,
+    },
+    Directory {
+        name: String,
+        status: Option<String>,
+        oversized: bool,
+        dirty: bool,
+    },
+    Numstat(Numstat),
+    DiffStat(DiffStat),
+    Annotation(String),
     Connector(String),
-    Indent(String),
+    /// Shared by all the lines of a directory, rather than copied for each of them.
+    Indent(Rc<str>),
+    /// The `Options::status_glyphs` marker before a file, blank for `None`.
+    StatusGlyph(Option<String>),
+    /// Stands in for the entries `Options::max_siblings` leaves out, e.g. `+7 more files`.
+    Omitted(String),
+    /// The `Options::icons` glyph before a file.
+    Icon(&'static str),
+    /// The line counts of a file for `Options::bars`, scaled once all of them are known.
+    Bar(Numstat),
 }
 
+#[derive(Default, Clone)]
 pub struct Options {
     pub compact: bool,
     pub color: bool,
+    pub columns: Option<usize>,
+    pub ignore_case_dedup: bool,
+    /// Full path of the file to highlight as the one currently open.
+    pub current: Option<String>,
+    pub guides_only: bool,
+    pub sort_dirs_by_count: bool,
+    /// List directories before files within each level.
+    pub dirs_first: bool,
+    /// List files before directories within each level.
+    pub files_first: bool,
+    /// Color file names from recent (bright) to old (dim) when their ages are known.
+    pub age_gradient: bool,
+    /// Print the full path of every entry instead of a tree.
+    pub flat: bool,
+    /// Separate `flat` paths with NUL instead of newlines.
+    pub print0: bool,
+    /// Tint directories with more direct children than this.
+    pub warn_dir_size: Option<usize>,
+    pub trim_common_prefix: bool,
+    /// Print the tree as a nested JSON document.
+    pub json: bool,
+    /// Wrap file names so that lines fit within this many columns.
+    pub wrap: Option<usize>,
+    /// Put runs of sibling files on shared comma-separated lines.
+    pub merge_files: bool,
+    /// Terminal width used by `merge_files`.
+    pub width: Option<usize>,
+    /// Split paths on `\` as well as `/`.
+    pub mixed_separators: bool,
+    /// Print top-level entries without a connector.
+    pub bare_root: bool,
+    /// Paths of submodules, shown as single entries without their contents.
+    pub submodules: Vec<String>,
+    /// Give each top-level directory its own headed tree.
+    pub per_root: bool,
+    /// Labels shown after files, keyed by their full path.
+    pub annotations: HashMap<String, String>,
+    /// Print each file's status after its name in this form.
+    pub status_display: Option<StatusDisplay>,
+    /// Print one JSON object per file instead of a tree.
+    pub jsonl: bool,
+    /// Emphasize directories that lead to changes and dim the rest.
+    pub highlight_dirty_path: bool,
+    /// Print one line per file in this format instead of a tree.
+    pub format: Option<Template>,
+    /// Start the tree at this depth, where top-level entries are at depth 1.
+    pub min_depth: Option<usize>,
+    /// Splice out directories whose only child is a directory. Unlike `compact`, this changes
+    /// the tree instead of the names, so nothing is left for `compact` to join.
+    pub flatten_single: bool,
+    /// Print an HTML page where directories can be expanded and collapsed.
+    pub html_interactive: bool,
+    /// What each level is ordered by first, ties keeping the order of `sort`.
+    pub sort_key: SortKey,
+    /// Show the most important status below each directory after its name.
+    pub dir_status: bool,
+    /// Status codes from most to least important. Empty means `DEFAULT_STATUS_PRIORITY`.
+    pub status_priority: Vec<String>,
+    /// Replace files sharing an extension within a directory by one `*.ext (N)` entry.
+    pub fold_extensions: bool,
+    /// How names are ordered within each level.
+    pub sort: SortOrder,
+    /// Only `compact` chains of at least this many directories.
+    pub compact_threshold: Option<usize>,
+    /// Keep only paths with one of these statuses. Empty keeps everything.
+    pub only: Vec<String>,
+    /// Stop after this many output lines. `Some(0)` means no limit.
+    pub max_total_lines: Option<usize>,
+    /// Explain the statuses in the tree on a line above or below it.
+    pub legend: Option<LegendPosition>,
+    /// Statuses for input paths that come without one, keyed by the path as given.
+    pub status_map: HashMap<String, String>,
+    /// Alternate the colors of the segments in compacted directory names like `after/lsp`.
+    pub segment_colors: bool,
+    /// Leave out files and directories whose names start with `.`, like `ls` without `-a`.
+    pub hide_hidden: bool,
+    /// Follow the tree with the number of files per extension, e.g. `.lua: 11, .json: 1`. With
+    /// `json`, only the counts are printed, as an object.
+    pub ext_summary: bool,
+    /// Keep directories that the filters leave with nothing in them and that have no status of
+    /// their own, which are otherwise removed. Directories listed themselves are always kept.
+    pub keep_empty_dirs: bool,
+    /// Give files with a status a background in their status color, with black text on it.
+    pub status_bg: bool,
+    /// The characters the tree is drawn with.
+    pub tree_chars: TreeChars,
+    /// Instead of the tree, print one line per top-level directory counting its statuses, e.g.
+    /// `src/    M2 A1`. Top-level files are counted together under `./`.
+    pub dashboard: bool,
+    /// Put a `●` in the status color before each file with a status, instead of coloring its
+    /// name. Files without a status get a blank in its place, keeping the names lined up.
+    pub status_glyphs: bool,
+    /// Make files with these extensions, given without the dot, bold and underlined. Case is
+    /// ignored.
+    pub highlight_extensions: Vec<String>,
+    /// Follow each directory with how many entries of this kind are below it, e.g. `(3 files)`.
+    pub counts: Option<CountMode>,
+    /// Normalize the paths to this form, so that names spelled with precomposed characters and
+    /// with combining marks end up in the same node.
+    pub normalize_unicode: Option<NormalizationForm>,
+    /// Print each leaf as a folded stack for flame graph tools, `src;main.rs 15`, weighted by
+    /// its changed lines when known and 1 otherwise.
+    pub folded: bool,
+    /// Show directories whose name or path matches this pattern as a single line with how many
+    /// entries are below them, e.g. `node_modules (… 120 entries)`, instead of expanding them.
+    pub collapse_pattern: Option<Regex>,
+    /// Start the tree with a line counting every status in it, e.g. `● 2M 1A 1D 3??`.
+    pub root_status: bool,
+    /// Put a guide line above and below the entries of each directory, bracketing them.
+    pub brackets: bool,
+    /// Show at most this many files and this many directories in each directory, followed by
+    /// a line like `+7 more files` for the rest.
+    pub max_siblings: Option<usize>,
+    /// Rename the first component of each path, `(from, to)`, before it goes into the tree. The
+    /// first matching pair wins, and a `to` of `.` drops the component.
+    pub aliases: Vec<(String, String)>,
+    /// Rename every component with `aliases`, not just the first.
+    pub alias_all: bool,
+    /// Reverse the order `sort` and `sort_key` give each level. `dirs_first` and the other
+    /// groupings still apply.
+    pub reverse: bool,
+    /// Follow each name with its depth, e.g. `lua [d2]`, to check depth-dependent options.
+    pub show_depth: bool,
+    /// What `compact` joins directory names with, e.g. ` › `, instead of `/`.
+    pub compact_separator: Option<String>,
+    /// The git status each status code of another tool stands for, e.g. `changed` → `M`. The
+    /// codes are translated before anything else looks at them.
+    pub status_codes: HashMap<String, String>,
+    /// Put a Nerd Font glyph for its type before each file name.
+    pub icons: bool,
+    /// List the `icons` glyphs of the extensions in the tree on a line above or below it.
+    pub icon_legend: Option<LegendPosition>,
+    /// The case names are shown in. Only the text changes, not the tree or its order.
+    pub path_case: PathCase,
+    /// Follow each file with its changed lines and a `+++--` bar like `git diff --stat`, scaled
+    /// so that the largest change fits in `BAR_WIDTH`. Directories keep their totals.
+    pub bars: bool,
+    /// Move untracked (`??`) and ignored (`!!`) entries after all the others of their level,
+    /// whatever the other orderings say.
+    pub untracked_last: bool,
+    /// The colors of statuses, directories and guides.
+    pub palette: Palette,
+    /// Whether a one-letter `only` code needs either column of a two-letter status to match,
+    /// or both of them.
+    pub merge_status_columns: ColumnMerge,
+    /// What `color` applies to.
+    pub color_scope: ColorScope,
+    /// List only changed files, each as a top-level entry named by its full path.
+    pub tree_only_changed_leaves: bool,
+    /// Build every line's indent on its own instead of sharing one between siblings, the way
+    /// it was done before. The output is the same; this is only there for tests and benchmarks
+    /// to compare against.
+    #[doc(hidden)]
+    pub unshared_prefixes: bool,
 }
 
-pub fn generate_tree_from_paths(
-    paths_with_status: &Vec<(String, String)>,
-    options: &Options,
-) -> String {
-    let mut root = Tree::new();
-    for (path_str, status) in paths_with_status {
-        if !path_str.trim().is_empty() {
-            let status_opt = if status.is_empty() {
-                None
+#[derive(clap::ValueEnum, Debug, Default, Clone, Copy, PartialEq)]
+pub enum LegendPosition {
+    Top,
+    #[default]
+    Bottom,
+}
+
+/// The base order of each level, before `dirs_first` and the other orderings apply.
+#[derive(clap::ValueEnum, Debug, Default, Clone, Copy, PartialEq)]
+pub enum SortOrder {
+    /// By the bytes of the names.
+    #[default]
+    Name,
+    /// Like a dictionary: accents and case only break ties.
+    Locale,
+    /// In the order the paths first appear in the input.
+    #[value(alias = "none")]
+    Input,
+}
+
+/// The main ordering of each level, on top of `SortOrder`.
+#[derive(clap::ValueEnum, Debug, Default, Clone, Copy, PartialEq)]
+pub enum SortKey {
+    /// Only by `SortOrder`.
+    #[default]
+    Name,
+    /// As the full paths would sort, as if each directory name ended in `/`.
+    Path,
+    /// By extension, grouping files of the same type. Entries without one come first.
+    Ext,
+    /// In `status_priority` order, by the most important status of each entry.
+    Status,
+}
+
+/// How `Options::path_case` changes the names shown.
+#[derive(clap::ValueEnum, Debug, Default, Clone, Copy, PartialEq)]
+pub enum PathCase {
+    Lower,
+    Upper,
+    /// As they are.
+    #[default]
+    Preserve,
+}
+
+impl PathCase {
+    fn apply(self, name: String) -> String {
+        match self {
+            PathCase::Lower => name.to_lowercase(),
+            PathCase::Upper => name.to_uppercase(),
+            PathCase::Preserve => name,
+        }
+    }
+}
+
+/// What `Options::color_scope` colors.
+#[derive(clap::ValueEnum, Debug, Default, Clone, Copy, PartialEq)]
+pub enum ColorScope {
+    /// File and directory names, but not the tree guides.
+    Names,
+    /// Names and tree guides.
+    #[default]
+    All,
+    /// Only status glyphs and the annotations after names, leaving names and guides plain.
+    StatusOnly,
+}
+
+/// How `Options::merge_status_columns` combines the two columns of a status like `MM`.
+#[derive(clap::ValueEnum, Debug, Default, Clone, Copy, PartialEq)]
+pub enum ColumnMerge {
+    /// `M` keeps `MM`, `AM`, `M.` and `.M`.
+    #[default]
+    Or,
+    /// `M` keeps only `MM`.
+    And,
+}
+
+/// What the `Options::counts` badge of a directory counts, at any depth below it.
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum CountMode {
+    Files,
+    Dirs,
+    /// Files and directories.
+    All,
+}
+
+/// A preset of the characters the tree is drawn with.
+#[derive(clap::ValueEnum, Debug, Default, Clone, Copy, PartialEq)]
+pub enum TreeChars {
+    /// `├──`, `└──` and `│`.
+    #[default]
+    Utf8,
+    /// `|--`, `` `-- `` and `|`, for terminals and files without Unicode.
+    Ascii,
+    /// Like `utf8`, with `╰──` for the last entry.
+    Rounded,
+    /// Double lines: `╠══`, `╚══` and `║`.
+    Double,
+}
+
+/// The pieces each line of the tree starts with.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Connectors {
+    /// Before an entry with more entries after it.
+    pub branch: &'static str,
+    /// Before the last entry of a directory.
+    pub last: &'static str,
+    /// Under an entry with more entries after it, to carry the line down to them.
+    pub vertical: &'static str,
+    /// Under the last entry of a directory.
+    pub blank: &'static str,
+    /// Above the first entry of a directory, with `Options::brackets`.
+    pub open: &'static str,
+    /// Below the last entry of a directory, with `Options::brackets`.
+    pub close: &'static str,
+}
+
+impl TreeChars {
+    pub fn connectors(self) -> Connectors {
+        let (branch, last, vertical, open, close) = match self {
+            TreeChars::Utf8 => ("├── ", "└── ", "│   ", "┌─", "└─"),
+            TreeChars::Ascii => ("|-- ", "`-- ", "|   ", ",-", "`-"),
+            TreeChars::Rounded => ("├── ", "╰── ", "│   ", "╭─", "╰─"),
+            TreeChars::Double => ("╠══ ", "╚══ ", "║   ", "╔═", "╚═"),
+        };
+        Connectors {
+            branch,
+            last,
+            vertical,
+            blank: "    ",
+            open,
+            close,
+        }
+    }
+}
+
+/// Conflicts first, then the changes that are easiest to miss, and untracked files last.
+pub const DEFAULT_STATUS_PRIORITY: [&str; 8] = ["U", "D", "A", "R", "C", "M", "T", "??"];
+
+/// How `Options::status_display` spells a status such as `MM`.
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum StatusDisplay {
+    /// As git printed it: `MM`.
+    Raw,
+    /// One letter when both columns agree: `M`.
+    Single,
+    /// In words: `modified (staged+unstaged)`.
+    Verbose,
+}
+
+/// Colors for `age_gradient` mode, from the oldest files to the newest.
+const AGE_GRADIENT: [Color; 5] = [
+    Color::BrightBlack,
+    Color::White,
+    Color::Yellow,
+    Color::BrightYellow,
+    Color::BrightRed,
+];
+
+/// Nerd Font glyphs for `Options::icons`, by extension.
+const ICONS: [(&str, &str); 13] = [
+    ("c", "\u{e61e}"),
+    ("css", "\u{e749}"),
+    ("go", "\u{e626}"),
+    ("html", "\u{e736}"),
+    ("js", "\u{e74e}"),
+    ("json", "\u{e60b}"),
+    ("lua", "\u{e620}"),
+    ("md", "\u{e609}"),
+    ("py", "\u{e606}"),
+    ("rs", "\u{e7a8}"),
+    ("sh", "\u{f489}"),
+    ("toml", "\u{e615}"),
+    ("ts", "\u{e628}"),
+];
+
+/// The `Options::icons` glyph of files with an extension `ICONS` does not list.
+const DEFAULT_ICON: &str = "\u{f15b}";
+
+/// The longest `Options::bars` bar.
+const BAR_WIDTH: u64 = 20;
+
+/// Printed after the last line `max_total_lines` lets through.
+const TRUNCATION_NOTICE: &str = "… (truncated, use --max-total-lines 0 for all)";
+
+/// Used when no terminal width is known.
+const DEFAULT_WIDTH: usize = 80;
+
+/// Marks a file with a status in `status_glyphs` mode.
+const STATUS_GLYPH: &str = "●";
+
+/// Stands in for every name in `--guides-only` mode.
+const GUIDE_PLACEHOLDER: &str = "...";
+
+/// Spaces inserted between columns in `--columns` mode.
+const COLUMN_GUTTER: usize = 4;
+
+pub fn generate_tree_from_paths(paths: &[PathEntry], options: &Options) -> String {
+    render(&build_tree(paths, options), options)
+}
+
+/// Formats an already built tree, e.g. one from `build_tree` that was then changed, in the
+/// output mode `options` selects.
+pub fn render(root: &Tree, options: &Options) -> String {
+    let output = render_unlimited(root, options);
+    // Cutting JSON or HTML short would leave it unreadable.
+    let structured = options.json || options.jsonl || options.html_interactive;
+    match options.max_total_lines {
+        Some(max) if max > 0 && !structured => truncate_lines(output, max),
+        _ => output,
+    }
+}
+
+/// Keeps the first `max` lines of `output` and notes that the rest was cut off.
+fn truncate_lines(output: String, max: usize) -> String {
+    let mut lines = output.split_inclusive('\n');
+    let mut truncated: String = lines.by_ref().take(max).collect();
+    if lines.next().is_some() {
+        truncated.push_str(TRUNCATION_NOTICE);
+        truncated.push('\n');
+    }
+    truncated
+}
+
+fn render_unlimited(root: &Tree, options: &Options) -> String {
+    if let Some(template) = &options.format {
+        let mut result = String::new();
+        render_template(&mut result, root, "", 1, template, options);
+        return result;
+    }
+    if options.flat {
+        return render_flat(root, options);
+    }
+    if options.tree_only_changed_leaves {
+        return render_changed_leaves(root, options);
+    }
+    if options.folded {
+        let mut result = String::new();
+        render_folded(&mut result, root, "", options);
+        return result;
+    }
+    if options.jsonl {
+        let mut result = Vec::new();
+        write_json_lines(&mut result, root, "", 1, options).unwrap();
+        return String::from_utf8(result).unwrap();
+    }
+    if options.html_interactive {
+        let mut result = String::from(html::PAGE_START);
+        write_html_tree(&mut result, root, options);
+        result.push_str(html::PAGE_END);
+        return result;
+    }
+    if options.dashboard {
+        let groups = summarize_top_level(root);
+        let mut result = String::new();
+        if options.json {
+            write_json_dashboard(&mut result, &groups);
+            result.push('\n');
+        } else {
+            render_dashboard(&mut result, &groups, options);
+        }
+        return result;
+    }
+    if options.json {
+        let mut result = String::new();
+        if options.ext_summary {
+            write_json_ext_summary(&mut result, &count_extensions(root));
+        } else {
+            write_json_tree(&mut result, root, options);
+        }
+        result.push('\n');
+        return result;
+    }
+    let tree = if options.per_root {
+        render_per_root(root, options)
+    } else {
+        match options.columns {
+            Some(columns) if columns > 1 => render_columns(root, columns, options),
+            _ => render_entries(format_tree_as_entries(root, "", "", options), options),
+        }
+    };
+    let mut tree = match (options.legend, render_legend(root, options)) {
+        (Some(LegendPosition::Top), Some(legend)) => format!("{}\n{}", legend, tree),
+        (Some(LegendPosition::Bottom), Some(legend)) => format!("{}{}\n", tree, legend),
+        _ => tree,
+    };
+    if let (Some(position), Some(legend)) = (options.icon_legend, render_icon_legend(root)) {
+        tree = match position {
+            LegendPosition::Top => format!("{}\n{}", legend, tree),
+            LegendPosition::Bottom => format!("{}{}\n", tree, legend),
+        };
+    }
+    if options.ext_summary {
+        tree.push_str(&render_ext_summary(&count_extensions(root)));
+    }
+    if options.root_status {
+        tree.insert_str(0, &render_root_status(root, options));
+    }
+    tree
+}
+
+/// Counts the statuses of `node` and everything below it.
+fn count_statuses<'a>(node: &'a Node, counts: &mut HashMap<&'a str, usize>) {
+    if let Some(status) = node.status.as_deref() {
+        *counts.entry(status).or_default() += 1;
+    }
+    for child in node.children.iter().flat_map(|children| children.values()) {
+        count_statuses(child, counts);
+    }
+}
+
+/// The `Options::root_status` line, or nothing when no entry has a status. The statuses come
+/// in the order of the legend.
+fn render_root_status(root: &Tree, options: &Options) -> String {
+    let mut counts = HashMap::new();
+    for node in root.values() {
+        count_statuses(node, &mut counts);
+    }
+    if counts.is_empty() {
+        return String::new();
+    }
+    let mut counts: Vec<_> = counts.into_iter().collect();
+    counts.sort_by_key(|(status, _)| (status_rank(status, options), *status));
+    let mut line = String::from(STATUS_GLYPH);
+    for (status, count) in counts {
+        let code = format!("{}{}", count, status);
+        line.push(' ');
+        if options.color {
+            line.push_str(&options.palette.paint(&code, Some(status)).to_string());
+        } else {
+            line.push_str(&code);
+        }
+    }
+    line.push('\n');
+    line
+}
+
+/// A top-level directory for `Options::dashboard` and how many entries in it have each status.
+struct DashboardGroup<'a> {
+    name: &'a str,
+    /// Most common first.
+    statuses: Vec<(&'a str, usize)>,
+    total: usize,
+}
+
+/// Counts the statuses under each top-level directory, and those of the top-level files under
+/// `.`, with the most changed directories first.
+fn summarize_top_level(root: &Tree) -> Vec<DashboardGroup<'_>> {
+    let mut groups: BTreeMap<&str, HashMap<&str, usize>> = BTreeMap::new();
+    for (name, node) in root {
+        let group = if node.children.is_some() { name } else { "." };
+        count_statuses(node, groups.entry(group).or_default());
+    }
+    let mut groups: Vec<DashboardGroup> = groups
+        .into_iter()
+        .map(|(name, counts)| {
+            let mut statuses: Vec<_> = counts.into_iter().collect();
+            statuses
+                .sort_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then_with(|| a.cmp(b)));
+            DashboardGroup {
+                name,
+                total: statuses.iter().map(|(_, count)| count).sum(),
+                statuses,
+            }
+        })
+        .collect();
+    groups.sort_by_key(|group| Reverse(group.total));
+    groups
+}
+
+fn render_dashboard(out: &mut String, groups: &[DashboardGroup], options: &Options) {
+    let width = groups
+        .iter()
+        .map(|group| group.name.chars().count() + 1)
+        .max()
+        .unwrap_or(0);
+    for group in groups {
+        let name = format!("{}/", group.name);
+        if group.statuses.is_empty() {
+            out.push_str(&format!("{}\n", name));
+            continue;
+        }
+        out.push_str(&format!("{:<1$}", name, width + COLUMN_GUTTER));
+        for (i, (status, count)) in group.statuses.iter().enumerate() {
+            if i > 0 {
+                out.push(' ');
+            }
+            let code = format!("{}{}", status, count);
+            if options.color {
+                out.push_str(&options.palette.paint(&code, Some(status)).to_string());
             } else {
-                Some(status.clone())
-            };
-            add_path_to_tree(&mut root, Path::new(path_str), status_opt);
+                out.push_str(&code);
+            }
         }
+        out.push('\n');
     }
+}
 
-    let entries = format_tree_as_entries(&root, "", options.compact);
-    let mut result = String::new();
-    for entry in entries {
-        match entry {
-            LineEntry::File(s, status) => {
-                let colored_s = if options.color {
-                    apply_color(&s, status.as_deref())
-                } else {
-                    s.normal().to_string()
-                };
-                write!(&mut result, "{}\n", colored_s)
+/// `[{"name":"src","total":3,"statuses":{"M":2,"A":1}},…]`
+fn write_json_dashboard(out: &mut String, groups: &[DashboardGroup]) {
+    out.push('[');
+    for (i, group) in groups.iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        out.push_str("{\"name\":");
+        json::write_string(out, group.name);
+        out.push_str(&format!(",\"total\":{},\"statuses\":{{", group.total));
+        for (j, (status, count)) in group.statuses.iter().enumerate() {
+            if j > 0 {
+                out.push(',');
             }
-            LineEntry::Directory(s) => write!(
-                &mut result,
-                "{}\n",
-                if options.color {
-                    s.blue().to_string()
-                } else {
-                    s
+            json::write_string(out, status);
+            out.push_str(&format!(":{}", count));
+        }
+        out.push_str("}}");
+    }
+    out.push(']');
+}
+
+/// Counts the files in `tree` by extension, most common first and then by extension. Files
+/// without one are counted as `(none)`.
+fn count_extensions(tree: &Tree) -> Vec<(String, usize)> {
+    fn collect(tree: &Tree, counts: &mut HashMap<String, usize>) {
+        for (name, node) in tree {
+            match &node.children {
+                Some(children) => collect(children, counts),
+                None => {
+                    let extension = Path::new(name).extension().map_or_else(
+                        || "(none)".to_string(),
+                        |extension| format!(".{}", extension.to_string_lossy()),
+                    );
+                    *counts.entry(extension).or_default() += 1;
                 }
-            ),
-            LineEntry::Connector(s) | LineEntry::Indent(s) => write!(
-                &mut result,
-                "{}",
-                if options.color {
-                    s.bright_black().to_string()
-                } else {
-                    s
+            }
+        }
+    }
+    let mut counts = HashMap::new();
+    collect(tree, &mut counts);
+    let mut counts: Vec<_> = counts.into_iter().collect();
+    counts.sort_by(|(a, a_count), (b, b_count)| b_count.cmp(a_count).then_with(|| a.cmp(b)));
+    counts
+}
+
+/// `.lua: 11, .json: 1` on a line of its own, or nothing when there are no files.
+fn render_ext_summary(counts: &[(String, usize)]) -> String {
+    if counts.is_empty() {
+        return String::new();
+    }
+    let parts: Vec<String> = counts
+        .iter()
+        .map(|(extension, count)| format!("{}: {}", extension, count))
+        .collect();
+    format!("{}\n", parts.join(", "))
+}
+
+fn write_json_ext_summary(out: &mut String, counts: &[(String, usize)]) {
+    out.push('{');
+    for (i, (extension, count)) in counts.iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        json::write_string(out, extension);
+        out.push_str(&format!(":{}", count));
+    }
+    out.push('}');
+}
+
+/// Lists every status in `root` with its meaning, e.g. `M modified, ?? untracked`, or `None`
+/// if nothing has a status.
+fn render_legend(root: &Tree, options: &Options) -> Option<String> {
+    fn collect<'a>(tree: &'a Tree, statuses: &mut Vec<&'a str>, options: &Options) {
+        for (_, node) in sorted_children(tree, options) {
+            if let Some(status) = node.status.as_deref() {
+                if !statuses.contains(&status) {
+                    statuses.push(status);
                 }
-            ),
-            // LineEntry::Indent(s) => write!(&mut result, "{}", s),
+            }
+            if let Some(children) = &node.children {
+                collect(children, statuses, options);
+            }
         }
-        .unwrap();
     }
-    result
+    let mut statuses = Vec::new();
+    collect(root, &mut statuses, options);
+    if statuses.is_empty() {
+        return None;
+    }
+    statuses.sort_by_key(|status| (status_rank(status, options), *status));
+    let items: Vec<String> = statuses
+        .into_iter()
+        .map(|status| {
+            let code = if options.color {
+                options.palette.paint(status, Some(status)).to_string()
+            } else {
+                status.to_string()
+            };
+            format!("{} {}", code, format_status(status, StatusDisplay::Verbose))
+        })
+        .collect();
+    Some(items.join(", "))
+}
+
+/// Renders a fixed tree that uses every status color and directory style, so the palette can be
+/// checked on the current terminal.
+pub fn color_test(options: &Options) -> String {
+    let fixture = [
+        ("README.md", ""),
+        ("build/", "??"),
+        ("src/added.rs", "A"),
+        ("src/conflicted.rs", "U"),
+        ("src/copied.rs", "C"),
+        ("src/deleted.rs", "D"),
+        ("src/modified.rs", "M"),
+        ("src/renamed.rs", "R"),
+        ("src/untracked.rs", "??"),
+    ];
+    let paths: Vec<PathEntry> = fixture
+        .iter()
+        .map(|(path, status)| PathEntry::new(path, status))
+        .collect();
+    generate_tree_from_paths(&paths, options)
+}
+
+/// Builds the tree for `paths`, applying the options that change its structure (such as
+/// `trim_common_prefix` or `min_depth`) but none of the display ones.
+pub fn build_tree(paths: &[PathEntry], options: &Options) -> Tree {
+    build_tree_with_conflicts(paths, options).0
+}
+
+/// A path left out of the tree because a file and a directory would share a name, e.g. `a` and
+/// `a/b` both listed as files.
+#[derive(Debug, PartialEq)]
+pub struct PathConflict {
+    pub path: String,
 }
 
-fn apply_color(s: &str, status: Option<&str>) -> String {
-    match status {
-        Some("M") => s.yellow().to_string(),
-        Some("A") => s.green().to_string(),
-        Some("D") => s.red().to_string(),
-        Some("R") => s.cyan().to_string(),
-        Some("C") => s.magenta().to_string(),
-        Some("U") => s.red().bold().to_string(),
-        Some("??") => s.bright_black().to_string(),
-        _ => s.normal().to_string(),
+impl std::fmt::Display for PathConflict {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "`{}` conflicts with a file or directory of the same name and was left out",
+            self.path
+        )
     }
 }
 
-fn add_path_to_tree(tree: &mut Tree, path: &Path, status: Option<String>) {
-    let mut current_tree = tree;
+/// Lists the input paths that the tree has no place for, in input order.
+pub fn find_path_conflicts(paths: &[PathEntry], options: &Options) -> Vec<PathConflict> {
+    build_tree_with_conflicts(paths, options).1
+}
 
-    let components: Vec<_> = path
-        .components()
-        .filter_map(|c| {
-            let name = c.as_os_str().to_string_lossy().into_owned();
-            if name == "/" || name.ends_with(":\\") {
+fn build_tree_with_conflicts(paths: &[PathEntry], options: &Options) -> (Tree, Vec<PathConflict>) {
+    let mut root = Tree::new();
+    let mut conflicts = Vec::new();
+    for entry in paths {
+        if let Some((path, status)) = filter_entry(entry, options) {
+            let status_opt = if status.is_empty() {
                 None
             } else {
-                Some(name)
+                Some(status.clone())
+            };
+            // A trailing slash marks a directory, e.g. git's `?? build/` for an untracked one.
+            let leaf = if path.ends_with('/') {
+                Node {
+                    listed: true,
+                    ..Node::new_directory(status_opt)
+                }
+            } else {
+                Node {
+                    diffstat: entry.diffstat.clone(),
+                    timestamp: entry.timestamp,
+                    ..Node::new_file(status_opt, entry.numstat)
+                }
+            };
+            if !add_path_to_tree(&mut root, Path::new(&path), leaf, options.ignore_case_dedup) {
+                conflicts.push(PathConflict {
+                    path: entry.path.clone(),
+                });
+            }
+        }
+    }
+    if options.hide_hidden {
+        root = prune_hidden(root);
+    }
+    if options.trim_common_prefix {
+        root = trim_common_prefix(root);
+    }
+    if options.flatten_single {
+        root = flatten_single_child_dirs(root);
+    }
+    if options.fold_extensions {
+        root = fold_extensions(root, options);
+    }
+    if let Some(min_depth) = options.min_depth {
+        root = start_at_depth(root, min_depth);
+    }
+    if !options.keep_empty_dirs {
+        root = prune_empty_dirs(root);
+    }
+    (root, conflicts)
+}
+
+/// The path `entry` goes in the tree under and its status, after `status_map`, or `None` if
+/// `only` filters it out.
+fn filter_entry<'a>(entry: &'a PathEntry, options: &'a Options) -> Option<(String, &'a String)> {
+    let status = match options.status_map.get(&entry.path) {
+        Some(status) if entry.status.is_empty() => status,
+        _ => &entry.status,
+    };
+    let status = options.status_codes.get(status).unwrap_or(status);
+    if entry.path.trim().is_empty() || !matches_only(status, options) {
+        return None;
+    }
+    let mut path = if options.mixed_separators {
+        entry.path.replace('\\', "/")
+    } else {
+        entry.path.clone()
+    };
+    if let Some(form) = options.normalize_unicode {
+        path = unicode::normalize(&path, form);
+    }
+    // Anything inside a submodule is folded into the submodule itself.
+    if let Some(submodule) = find_submodule(&path, options) {
+        path = submodule.to_string();
+    }
+    if !options.aliases.is_empty() {
+        path = apply_aliases(&path, options);
+        if path.is_empty() {
+            return None;
+        }
+    }
+    Some((path, status))
+}
+
+/// Renames the components of `path` that `Options::aliases` lists.
+fn apply_aliases(path: &str, options: &Options) -> String {
+    let components: Vec<&str> = path
+        .split('/')
+        .enumerate()
+        .filter_map(|(i, component)| {
+            if i > 0 && !options.alias_all {
+                return Some(component);
+            }
+            match options.aliases.iter().find(|(from, _)| from == component) {
+                Some((_, to)) if to == "." => None,
+                Some((_, to)) => Some(to.as_str()),
+                None => Some(component),
             }
         })
         .collect();
+    components.join("/")
+}
 
-    if components.is_empty() {
-        return;
+/// Writes `entry` the way `flat` mode, or `jsonl` mode if set, lists it, so that paths can be
+/// shown as they are read. The filters that only need the entry itself apply, but nothing is
+/// sorted or merged with the other entries.
+pub fn write_streamed_entry(
+    out: &mut impl Write,
+    entry: &PathEntry,
+    options: &Options,
+) -> io::Result<()> {
+    let Some((path, status)) = filter_entry(entry, options) else {
+        return Ok(());
+    };
+    let components: Vec<&str> = path.split('/').filter(|c| !c.is_empty()).collect();
+    if components.is_empty()
+        || (options.hide_hidden && components.iter().any(|c| c.starts_with('.')))
+    {
+        return Ok(());
     }
+    if options.jsonl {
+        let mut line = String::from("{\"path\":");
+        json::write_string(&mut line, &path);
+        line.push_str(",\"status\":");
+        if status.is_empty() {
+            line.push_str("null");
+        } else {
+            json::write_string(&mut line, status);
+        }
+        writeln!(out, "{},\"depth\":{}}}", line, components.len())
+    } else if options.print0 {
+        write!(out, "{}\0", path)
+    } else {
+        writeln!(out, "{}", path)
+    }
+}
 
-    let last_index = components.len() - 1;
-    for (i, component_name) in components.into_iter().enumerate() {
-        if i == last_index {
-            current_tree
-                .entry(component_name)
-                .or_insert_with(|| Node::new_file(status.clone()));
+/// Removes the entries whose names start with `.`.
+fn prune_hidden(tree: Tree) -> Tree {
+    let mut pruned = Tree::new();
+    for (name, mut node) in tree {
+        if name.starts_with('.') {
             continue;
         }
-        let entry = current_tree
-            .entry(component_name)
-            .or_insert_with(Node::new_directory);
-
-        if let Some(subtree) = &mut entry.children {
-            current_tree = subtree;
-        } else {
-            break;
+        if let Some(children) = node.children.take() {
+            node.children = Some(prune_hidden(children));
         }
+        pruned.insert(name, node);
     }
+    pruned
 }
 
-/// Recursively builds a vector of LineEntry structs representing the tree structure.
-fn format_tree_as_entries(tree: &Tree, prefix: &str, compact: bool) -> Vec<LineEntry> {
-    let mut entries = Vec::new();
-    let mut iter = tree.iter().peekable();
-    while let Some((name, node)) = iter.next() {
-        let mut compacted_name = name.clone();
-        let mut node_to_print = node;
+/// Removes the directories that the filters left with nothing in them. Directories that were
+/// listed themselves or have a status of their own are kept.
+fn prune_empty_dirs(tree: Tree) -> Tree {
+    let mut pruned = Tree::new();
+    for (name, mut node) in tree {
+        if let Some(children) = node.children.take() {
+            let children = prune_empty_dirs(children);
+            if children.is_empty() && !node.listed && node.status.is_none() {
+                continue;
+            }
+            node.children = Some(children);
+        }
+        pruned.insert(name, node);
+    }
+    pruned
+}
 
-        if compact {
-            while let Some(current_subtree) = &node_to_print.children {
-                if current_subtree.len() != 1 {
-                    break;
-                }
-                let (child_name, child_node) = current_subtree.iter().next().unwrap();
-                if child_node.children.is_some() {
-                    compacted_name.push('/');
-                    compacted_name.push_str(child_name);
-                    node_to_print = child_node;
-                    continue;
+/// Replaces the files of each level that share an extension with a single `*.ext (N)` file,
+/// which takes the most important status among them. Extensions with only one file are left
+/// alone.
+fn fold_extensions(tree: Tree, options: &Options) -> Tree {
+    let mut groups: HashMap<String, (usize, Option<String>)> = HashMap::new();
+    for (name, node) in &tree {
+        if let (None, Some(extension)) = (&node.children, Path::new(name).extension()) {
+            let (count, status) = groups
+                .entry(extension.to_string_lossy().into_owned())
+                .or_default();
+            *count += 1;
+            if let Some(new) = &node.status {
+                if status
+                    .as_ref()
+                    .is_none_or(|old| status_rank(new, options) < status_rank(old, options))
+                {
+                    *status = Some(new.clone());
                 }
-                break;
             }
         }
+    }
+    let mut folded = Tree::new();
+    for (name, mut node) in tree {
+        if let Some(children) = node.children.take() {
+            node.children = Some(fold_extensions(children, options));
+            folded.insert(name, node);
+            continue;
+        }
+        match Path::new(&name).extension().map(|e| e.to_string_lossy()) {
+            Some(extension) if groups[extension.as_ref()].0 > 1 => {
+                let (count, status) = &groups[extension.as_ref()];
+                let group = format!("*.{} ({})", extension, count);
+                folded.insert(group, Node::new_file(status.clone(), None));
+            }
+            _ => {
+                folded.insert(name, node);
+            }
+        }
+    }
+    folded
+}
 
-        let is_last = iter.peek().is_none();
-        let connector = if is_last { "└── " } else { "├── " };
-
-        entries.push(LineEntry::Indent(prefix.to_string()));
-        entries.push(LineEntry::Connector(connector.to_string()));
-        entries.push(if node_to_print.children.is_some() {
-            LineEntry::Directory(compacted_name)
-        } else {
-            LineEntry::File(compacted_name, node_to_print.status.clone())
-        });
-
-        if let Some(subtree) = &node_to_print.children {
-            let new_prefix = format!("{}{}", prefix, if is_last { "    " } else { "│   " });
-            entries.extend(format_tree_as_entries(subtree, &new_prefix, compact));
+/// Replaces every directory whose only child is a directory with that child. A directory
+/// stays when it has a status of its own or when its child would clash with a sibling.
+fn flatten_single_child_dirs(tree: Tree) -> Tree {
+    let names: HashSet<String> = tree.keys().cloned().collect();
+    let mut flattened = Tree::new();
+    for (mut name, mut node) in tree {
+        if let Some(children) = node.children.take() {
+            let mut children = flatten_single_child_dirs(children);
+            let promotable = node.status.is_none()
+                && children.len() == 1
+                && children.iter().all(|(child_name, child)| {
+                    child.children.is_some()
+                        && !names.contains(child_name)
+                        && !flattened.contains_key(child_name)
+                });
+            if promotable {
+                (name, node) = children.pop_first().unwrap();
+            } else {
+                node.children = Some(children);
+            }
         }
+        flattened.insert(name, node);
     }
-    entries
+    flattened
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// Drops the levels above `depth`, so that entries at `depth` become the top level. They are
+/// named by their path from the old root, and files in the dropped levels are left out.
+fn start_at_depth(root: Tree, depth: usize) -> Tree {
+    if depth <= 1 {
+        return root;
+    }
+    let mut lifted = Tree::new();
+    for (name, node) in root {
+        let Some(children) = node.children else {
+            continue;
+        };
+        for (child_name, child) in start_at_depth(children, depth - 1) {
+            lifted.insert(join_path(&name, &child_name), child);
+        }
+    }
+    lifted
+}
 
-    fn create_paths_with_status(paths: &[&str]) -> Vec<(String, String)> {
-        paths
-            .iter()
-            .map(|&s| (s.to_string(), String::new()))
-            .collect()
+/// Strips the directories every path shares, so the tree starts where the paths diverge.
+fn trim_common_prefix(mut root: Tree) -> Tree {
+    loop {
+        let shared = root.len() == 1
+            && root.values().all(|node| {
+                node.status.is_none() && node.children.as_ref().is_some_and(|c| !c.is_empty())
+            });
+        if !shared {
+            return root;
+        }
+        root = root.pop_first().unwrap().1.children.unwrap();
     }
+}
 
-    #[test]
-    fn test_generate_tree() {
-        assert_eq!(
-            generate_tree_from_paths(
-                &create_paths_with_status(&[
-                    "nvim/after/lsp/tailwindcss.lua",
-                    "nvim/after/lsp/ts_ls.lua",
-                    "nvim/after/lsp/denols.lua",
-                    "nvim/lazy-lock.json",
-                    "nvim/lua/setup-lazynvim.lua",
-                    "nvim/lua/install-lazynvim.lua",
-                    "nvim/lua/options.lua",
+fn render_entries(entries: Vec<LineEntry>, options: &Options) -> String {
+    style_entries(entries, options)
+        .into_iter()
+        .map(|line| {
+            let mut rendered: String = line.iter().map(ToString::to_string).collect();
+            rendered.push('\n');
+            rendered
+        })
+        .collect()
+}
+
+/// Styles the entries, yielding the segments that make up each output line.
+fn style_entries(entries: Vec<LineEntry>, options: &Options) -> Vec<Vec<ColoredString>> {
+    let timestamps = entries.iter().filter_map(|entry| match entry {
+        LineEntry::File { timestamp, .. } => *timestamp,
+        _ => None,
+    });
+    let age_range = timestamps.clone().min().zip(timestamps.max());
+    let max_change = entries
+        .iter()
+        .filter_map(|entry| match entry {
+            LineEntry::Bar(numstat) => Some(numstat.added? + numstat.deleted?),
+            _ => None,
+        })
+        .max()
+        .unwrap_or(0);
+    let compact_separator = options.compact_separator.as_deref().unwrap_or("/");
+    let color_names = options.color && options.color_scope != ColorScope::StatusOnly;
+    let color_connectors = options.color && options.color_scope == ColorScope::All;
+    let mut lines: Vec<Vec<ColoredString>> = Vec::new();
+    for mut entry in entries {
+        if let LineEntry::File { name, .. }
+        | LineEntry::Directory { name, .. }
+        | LineEntry::Submodule { name, .. } = &mut entry
+        {
+            *name = options.path_case.apply(std::mem::take(name));
+        }
+        // Every line starts with its indent, so that is where the previous line ends.
+        if matches!(entry, LineEntry::Indent(_)) || lines.is_empty() {
+            lines.push(Vec::new());
+        }
+        let line = lines.last_mut().unwrap();
+        match entry {
+            LineEntry::File {
+                name,
+                status,
+                current,
+                timestamp,
+            } => line.push(if color_names {
+                let colored_s = match (timestamp, age_range) {
+                    (Some(timestamp), Some(range)) if options.age_gradient => {
+                        apply_age_color(&name, timestamp, range)
+                    }
+                    _ if options.status_glyphs => name.normal(),
+                    _ => options.palette.paint(&name, status.as_deref()),
+                };
+                let colored_s = match options.palette.background(status.as_deref()) {
+                    Some(background) if options.status_bg => colored_s.black().on_color(background),
+                    _ => colored_s,
+                };
+                let colored_s = match options.palette.highlight {
+                    _ if !has_highlighted_extension(&name, options) => colored_s,
+                    Some(color) => colored_s.color(color).bold().underline(),
+                    None => colored_s.bold().underline(),
+                };
+                if current {
+                    colored_s.bold().reversed()
+                } else {
+                    colored_s
+                }
+            } else {
+                name.into()
+            }),
+            LineEntry::Submodule { name, status } => line.push(if !color_names {
+                name.into()
+            } else if status.is_some() {
+                options.palette.paint(&name, status.as_deref())
+            } else {
+                name.bright_cyan()
+            }),
+            LineEntry::Directory {
+                name,
+                status,
+                oversized,
+                dirty,
+            } if color_names
+                && options.segment_colors
+                && !oversized
+                && status.is_none()
+                && (dirty || !options.highlight_dirty_path)
+                && name.contains(compact_separator) =>
+            {
+                for (i, segment) in name.split(compact_separator).enumerate() {
+                    if i > 0 {
+                        line.push(if color_connectors {
+                            compact_separator.color(options.palette.connector)
+                        } else {
+                            compact_separator.into()
+                        });
+                    }
+                    let colored = if i % 2 == 0 {
+                        segment.blue()
+                    } else {
+                        segment.cyan()
+                    };
+                    line.push(if dirty { colored.bold() } else { colored });
+                }
+            }
+            LineEntry::Directory {
+                name,
+                status,
+                oversized,
+                dirty,
+            } => line.push(if !color_names {
+                name.into()
+            } else if oversized {
+                name.bright_red()
+            } else if status.is_some() {
+                options.palette.paint(&name, status.as_deref())
+            } else if options.highlight_dirty_path {
+                if dirty {
+                    name.bright_blue().bold()
+                } else {
+                    name.bright_black()
+                }
+            } else {
+                name.color(options.palette.directory)
+            }),
+            LineEntry::Annotation(s) => {
+                line.push(" ".into());
+                line.push(if options.color {
+                    s.bright_black()
+                } else {
+                    s.into()
+                });
+            }
+            LineEntry::Numstat(numstat) => line.extend(style_numstat(numstat, options.color)),
+            LineEntry::DiffStat(diffstat) => line.extend(style_diffstat(diffstat, options.color)),
+            LineEntry::Connector(s) => line.push(if color_connectors {
+                s.color(options.palette.connector)
+            } else {
+                s.into()
+            }),
+            LineEntry::Indent(s) => line.push(if color_connectors {
+                s.color(options.palette.connector)
+            } else {
+                s.as_ref().into()
+            }),
+            LineEntry::Omitted(s) => line.push(if options.color {
+                s.bright_black()
+            } else {
+                s.into()
+            }),
+            LineEntry::Bar(numstat) => line.extend(style_bar(numstat, max_change, options.color)),
+            LineEntry::Icon(icon) => line.push(format!("{} ", icon).normal()),
+            LineEntry::StatusGlyph(status) => {
+                line.push(match status {
+                    Some(status) if options.color => {
+                        options.palette.paint(STATUS_GLYPH, Some(&status))
+                    }
+                    Some(_) => STATUS_GLYPH.into(),
+                    None => " ".into(),
+                });
+                line.push(" ".into());
+            }
+        }
+    }
+    lines
+}
+
+fn style_numstat(numstat: Numstat, color: bool) -> Vec<ColoredString> {
+    let (Some(added), Some(deleted)) = (numstat.added, numstat.deleted) else {
+        return vec![" (binary)".into()];
+    };
+    let added = format!("+{}", added);
+    let deleted = format!("-{}", deleted);
+    if color {
+        vec![
+            " (".into(),
+            added.green(),
+            " ".into(),
+            deleted.red(),
+            ")".into(),
+        ]
+    } else {
+        vec![format!(" ({} {})", added, deleted).into()]
+    }
+}
+
+/// ` 12 ++++----`, with the bar scaled down when `max_change` is more than `BAR_WIDTH`, or
+/// ` Bin` for a binary file.
+fn style_bar(numstat: Numstat, max_change: u64, color: bool) -> Vec<ColoredString> {
+    let (Some(added), Some(deleted)) = (numstat.added, numstat.deleted) else {
+        return vec![" Bin".into()];
+    };
+    // Rounded up, so that no change disappears from the bar.
+    let scale = |count: u64| match max_change {
+        0..=BAR_WIDTH => count,
+        _ => (count * BAR_WIDTH).div_ceil(max_change),
+    } as usize;
+    let mut segments = vec![format!(" {}", added + deleted).into()];
+    if added + deleted > 0 {
+        segments.push(" ".into());
+    }
+    let (added, deleted) = ("+".repeat(scale(added)), "-".repeat(scale(deleted)));
+    if color {
+        segments.push(added.green());
+        segments.push(deleted.red());
+    } else {
+        segments.push(format!("{}{}", added, deleted).into());
+    }
+    segments
+}
+
+fn style_diffstat(diffstat: DiffStat, color: bool) -> Vec<ColoredString> {
+    let Some(changes) = diffstat.changes else {
+        return vec![" (binary)".into()];
+    };
+    let mut segments = vec![format!(" ({}", changes).into()];
+    if !diffstat.bar.is_empty() {
+        segments.push(" ".into());
+        if color {
+            let added = diffstat.bar.chars().filter(|&c| c == '+').count();
+            let deleted = diffstat.bar.chars().filter(|&c| c == '-').count();
+            segments.push("+".repeat(added).green());
+            segments.push("-".repeat(deleted).red());
+        } else {
+            segments.push(diffstat.bar.into());
+        }
+    }
+    segments.push(")".into());
+    segments
+}
+
+/// Sums the line counts of every file below `tree`, or `None` if none of them has any.
+fn aggregate_numstat(tree: &Tree) -> Option<Numstat> {
+    let mut total: Option<Numstat> = None;
+    for node in tree.values() {
+        let numstat = match &node.children {
+            Some(subtree) => aggregate_numstat(subtree),
+            None => node.numstat,
+        };
+        if let Some(numstat) = numstat {
+            let total = total.get_or_insert(Numstat {
+                added: Some(0),
+                deleted: Some(0),
+            });
+            total.added = total.added.map(|n| n + numstat.added.unwrap_or(0));
+            total.deleted = total.deleted.map(|n| n + numstat.deleted.unwrap_or(0));
+        }
+    }
+    total
+}
+
+/// How a span should be drawn, for TUI libraries that apply styles themselves.
+#[cfg(feature = "tui")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Style {
+    pub fg: Option<Color>,
+    pub bg: Option<Color>,
+    pub modifiers: colored::Style,
+}
+
+/// Renders the tree as styled spans, one list per line, instead of ANSI escape sequences.
+/// Styles follow `options.color` just like the text output does.
+#[cfg(feature = "tui")]
+pub fn to_spans(paths: &[PathEntry], options: &Options) -> Vec<Vec<(String, Style)>> {
+    let root = build_tree(paths, options);
+    style_entries(format_tree_as_entries(&root, "", "", options), options)
+        .into_iter()
+        .map(|line| {
+            line.into_iter()
+                .map(|segment| {
+                    let style = Style {
+                        fg: segment.fgcolor,
+                        bg: segment.bgcolor,
+                        modifiers: segment.style,
+                    };
+                    (segment.input, style)
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Writes `tree` as an array of `{"name", "type", ...}` objects, with `children` holding the
+/// contents of directories and `status`/`added`/`deleted` present only when known.
+fn write_json_tree(out: &mut String, tree: &Tree, options: &Options) {
+    out.push('[');
+    for (i, (name, node)) in sorted_children(tree, options).into_iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        out.push_str("{\"name\":");
+        json::write_string(out, name);
+        out.push_str(",\"type\":");
+        json::write_string(
+            out,
+            if node.children.is_some() {
+                "directory"
+            } else {
+                "file"
+            },
+        );
+        if let Some(status) = &node.status {
+            out.push_str(",\"status\":");
+            json::write_string(out, status);
+        }
+        if let Some(numstat) = node.numstat {
+            let count = |n: Option<u64>| n.map_or("null".to_string(), |n| n.to_string());
+            out.push_str(&format!(
+                ",\"added\":{},\"deleted\":{}",
+                count(numstat.added),
+                count(numstat.deleted)
+            ));
+        }
+        if let Some(diffstat) = &node.diffstat {
+            let changes = diffstat
+                .changes
+                .map_or("null".to_string(), |n| n.to_string());
+            out.push_str(&format!(",\"changes\":{},\"bar\":", changes));
+            json::write_string(out, &diffstat.bar);
+        }
+        if let Some(timestamp) = node.timestamp {
+            out.push_str(&format!(",\"timestamp\":{}", timestamp));
+        }
+        if let Some(children) = &node.children {
+            out.push_str(",\"children\":");
+            write_json_tree(out, children, options);
+        }
+        out.push('}');
+    }
+    out.push(']');
+}
+
+/// Writes `{"path":…,"status":…,"depth":…}` for every leaf of the tree to `out`, one line each and
+/// in display order, so huge inputs can be streamed. Empty directories keep a trailing slash.
+pub fn write_tree_as_json_lines(
+    out: &mut impl Write,
+    paths: &[PathEntry],
+    options: &Options,
+) -> io::Result<()> {
+    write_json_lines(out, &build_tree(paths, options), "", 1, options)
+}
+
+fn write_json_lines(
+    out: &mut impl Write,
+    tree: &Tree,
+    parent_path: &str,
+    depth: usize,
+    options: &Options,
+) -> io::Result<()> {
+    for (name, node) in sorted_children(tree, options) {
+        let path = join_path(parent_path, name);
+        match &node.children {
+            Some(children) if !children.is_empty() => {
+                write_json_lines(out, children, &path, depth + 1, options)?;
+            }
+            children => {
+                let mut line = String::from("{\"path\":");
+                let suffix = if children.is_some() { "/" } else { "" };
+                json::write_string(&mut line, &format!("{}{}", path, suffix));
+                line.push_str(",\"status\":");
+                match &node.status {
+                    Some(status) => json::write_string(&mut line, status),
+                    None => line.push_str("null"),
+                }
+                writeln!(out, "{},\"depth\":{}}}", line, depth)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Fills in `template` for every leaf in display order, one line each.
+fn render_template(
+    out: &mut String,
+    tree: &Tree,
+    parent_path: &str,
+    depth: usize,
+    template: &Template,
+    options: &Options,
+) {
+    for (name, node) in sorted_children(tree, options) {
+        let path = join_path(parent_path, name);
+        match &node.children {
+            Some(children) if !children.is_empty() => {
+                render_template(out, children, &path, depth + 1, template, options);
+            }
+            children => {
+                let suffix = if children.is_some() { "/" } else { "" };
+                let fields = Fields {
+                    path: &format!("{}{}", path, suffix),
+                    name,
+                    status: node.status.as_deref(),
+                    depth,
+                };
+                template.render(out, &fields);
+                out.push('\n');
+            }
+        }
+    }
+}
+
+/// Writes `tree` as nested lists, with every directory in an open `<details>` element whose
+/// `<summary>` is its name.
+fn write_html_tree(out: &mut String, tree: &Tree, options: &Options) {
+    out.push_str("<ul>\n");
+    for (name, node) in sorted_children(tree, options) {
+        out.push_str("<li>");
+        let class = node.status.as_deref().and_then(html::status_class);
+        let open_tag = |out: &mut String, tag: &str| match class {
+            Some(class) => out.push_str(&format!("<{} class=\"{}\">", tag, class)),
+            None => out.push_str(&format!("<{}>", tag)),
+        };
+        match &node.children {
+            Some(children) => {
+                out.push_str("<details open>");
+                open_tag(out, "summary");
+                html::write_escaped(out, name);
+                out.push_str("</summary>\n");
+                write_html_tree(out, children, options);
+                out.push_str("</details>");
+            }
+            None => {
+                open_tag(out, "span");
+                html::write_escaped(out, name);
+                out.push_str("</span>");
+            }
+        }
+        out.push_str("</li>\n");
+    }
+    out.push_str("</ul>\n");
+}
+
+/// Serializes `tree` in the `--json` format, which `tree_from_json` reads back.
+pub fn tree_to_json(tree: &Tree) -> String {
+    let mut out = String::new();
+    write_json_tree(&mut out, tree, &Options::default());
+    out
+}
+
+/// Rebuilds a tree from the output of `tree_to_json`.
+pub fn tree_from_json(input: &str) -> Result<Tree, String> {
+    tree_from_json_value(json::parse(input)?)
+}
+
+fn tree_from_json_value(value: json::Value) -> Result<Tree, String> {
+    let json::Value::Array(items) = value else {
+        return Err("expected an array of entries".to_string());
+    };
+    let mut tree = Tree::new();
+    for item in items {
+        let json::Value::Object(members) = item else {
+            return Err("expected an entry object".to_string());
+        };
+        let mut name = None;
+        let mut node = Node::new_file(None, None);
+        let mut numstat = Numstat::default();
+        let mut diffstat = DiffStat::default();
+        for (key, value) in members {
+            match (key.as_str(), value) {
+                ("name", json::Value::String(s)) => name = Some(s),
+                ("type", json::Value::String(s)) if s == "directory" => {
+                    node.children.get_or_insert_with(Tree::new);
+                }
+                ("type", json::Value::String(s)) if s == "file" => {}
+                ("status", json::Value::String(s)) => node.status = Some(s),
+                ("added", value) => numstat.added = json_count(value)?,
+                ("deleted", value) => {
+                    numstat.deleted = json_count(value)?;
+                    node.numstat = Some(numstat);
+                }
+                ("changes", value) => diffstat.changes = json_count(value)?,
+                ("bar", json::Value::String(s)) => {
+                    diffstat.bar = s;
+                    node.diffstat = Some(diffstat.clone());
+                }
+                ("timestamp", value) => node.timestamp = json_count(value)?,
+                ("children", value) => node.children = Some(tree_from_json_value(value)?),
+                (key, _) => return Err(format!("unexpected value for `{}`", key)),
+            }
+        }
+        // Like `tree_to_flat`, count the directories that are empty or have a status as listed.
+        node.listed = node
+            .children
+            .as_ref()
+            .is_some_and(|children| children.is_empty() || node.status.is_some());
+        tree.insert(name.ok_or("entry without a name")?, node);
+    }
+    Ok(tree)
+}
+
+/// Reads a non-negative integer, or `None` from `null`.
+fn json_count(value: json::Value) -> Result<Option<u64>, String> {
+    match value {
+        json::Value::Null => Ok(None),
+        json::Value::Number(n) if n >= 0.0 && n.fract() == 0.0 => Ok(Some(n as u64)),
+        _ => Err("expected a count".to_string()),
+    }
+}
+
+/// Lists every file with its status, plus a `dir/` entry for directories that are empty or
+/// have a status of their own. `tree_from_flat` turns the list back into the same tree, minus
+/// line counts and timestamps.
+pub fn tree_to_flat(tree: &Tree) -> Vec<(String, Option<String>)> {
+    let mut entries = Vec::new();
+    collect_flat_entries(tree, "", &mut entries);
+    entries
+}
+
+fn collect_flat_entries(
+    tree: &Tree,
+    parent_path: &str,
+    entries: &mut Vec<(String, Option<String>)>,
+) {
+    for (name, node) in sorted_children(tree, &Options::default()) {
+        let path = join_path(parent_path, name);
+        match &node.children {
+            Some(children) => {
+                if children.is_empty() || node.status.is_some() {
+                    entries.push((format!("{}/", path), node.status.clone()));
+                }
+                collect_flat_entries(children, &path, entries);
+            }
+            None => entries.push((path, node.status.clone())),
+        }
+    }
+}
+
+/// Builds a tree from `(path, status)` pairs, where a trailing `/` marks a directory.
+pub fn tree_from_flat(entries: &[(String, Option<String>)]) -> Tree {
+    let paths: Vec<PathEntry> = entries
+        .iter()
+        .map(|(path, status)| PathEntry::new(path, status.as_deref().unwrap_or("")))
+        .collect();
+    build_tree(&paths, &Options::default())
+}
+
+/// Lists the full path of every leaf in display order. With `print0` the paths are separated
+/// by NUL, without a trailing one, so the output can be fed to `xargs -0`.
+fn render_flat(root: &Tree, options: &Options) -> String {
+    let mut paths = Vec::new();
+    collect_flat_paths(root, "", options, &mut paths);
+    if options.print0 {
+        paths.join("\0")
+    } else {
+        paths.iter().map(|path| format!("{}\n", path)).collect()
+    }
+}
+
+/// Lists every file and directory with a status as an entry of its own, named by its full path
+/// and followed by the status, sorted by path. Unchanged files and the directories in between
+/// are left out.
+fn render_changed_leaves(root: &Tree, options: &Options) -> String {
+    let mut changed: Vec<(String, String)> = tree_to_flat(root)
+        .into_iter()
+        .filter_map(|(path, status)| Some((path, status?)))
+        .collect();
+    changed.sort();
+    let connectors = options.tree_chars.connectors();
+    let display = options.status_display.unwrap_or(StatusDisplay::Raw);
+    let mut entries = Vec::new();
+    for (i, (path, status)) in changed.iter().enumerate() {
+        entries.push(LineEntry::Indent(Rc::from("")));
+        entries.push(LineEntry::Connector(
+            if i + 1 == changed.len() {
+                connectors.last
+            } else {
+                connectors.branch
+            }
+            .to_string(),
+        ));
+        entries.push(LineEntry::File {
+            name: path.clone(),
+            status: Some(status.clone()),
+            current: is_current(path, options),
+            timestamp: None,
+        });
+        entries.push(LineEntry::Annotation(format_status(status, display)));
+    }
+    render_entries(entries, options)
+}
+
+/// Writes `stack;of;names weight` for every leaf, as `flamegraph.pl` reads it.
+fn render_folded(out: &mut String, tree: &Tree, stack: &str, options: &Options) {
+    for (name, node) in sorted_children(tree, options) {
+        let stack = if stack.is_empty() {
+            name.clone()
+        } else {
+            format!("{};{}", stack, name)
+        };
+        match &node.children {
+            Some(children) if !children.is_empty() => {
+                render_folded(out, children, &stack, options);
+            }
+            _ => {
+                let changes = match (node.numstat, &node.diffstat) {
+                    (
+                        Some(Numstat {
+                            added: Some(added),
+                            deleted: Some(deleted),
+                        }),
+                        _,
+                    ) => Some(added + deleted),
+                    (_, Some(diffstat)) => diffstat.changes,
+                    _ => None,
+                };
+                out.push_str(&format!("{} {}\n", stack, changes.unwrap_or(1)));
+            }
+        }
+    }
+}
+
+fn collect_flat_paths(tree: &Tree, parent_path: &str, options: &Options, paths: &mut Vec<String>) {
+    for (name, node) in sorted_children(tree, options) {
+        let path = join_path(parent_path, name);
+        match &node.children {
+            // Directories without contents (e.g. untracked ones) keep their trailing slash.
+            Some(subtree) if subtree.is_empty() => paths.push(format!("{}/", path)),
+            Some(subtree) => collect_flat_paths(subtree, &path, options, paths),
+            None => paths.push(path),
+        }
+    }
+}
+
+/// Renders the top-level subtrees side by side, filling each column top to bottom
+/// before moving on to the next one (like `ls -C`).
+fn render_columns(root: &Tree, columns: usize, options: &Options) -> String {
+    if root.is_empty() {
+        return String::new();
+    }
+    let per_column = root.len().div_ceil(columns);
+    let order = sorted_children(root, options);
+    let mut blocks: Vec<Vec<String>> = Vec::new();
+    for entries in order.chunks(per_column) {
+        let chunk: Tree = entries
+            .iter()
+            .map(|&(name, node)| (name.clone(), node.clone()))
+            .collect();
+        let rendered = render_entries(format_tree_as_entries(&chunk, "", "", options), options);
+        blocks.push(rendered.lines().map(str::to_string).collect());
+    }
+
+    let widths: Vec<usize> = blocks
+        .iter()
+        .map(|lines| lines.iter().map(|l| visible_width(l)).max().unwrap_or(0))
+        .collect();
+    let height = blocks.iter().map(Vec::len).max().unwrap_or(0);
+
+    let mut result = String::new();
+    for row in 0..height {
+        // Only pad up to the last column that still has a line in this row.
+        let last = blocks.iter().rposition(|b| row < b.len()).unwrap_or(0);
+        for (i, block) in blocks.iter().enumerate().take(last + 1) {
+            let cell = block.get(row).map_or("", String::as_str);
+            result.push_str(cell);
+            if i < last {
+                let padding = widths[i] - visible_width(cell) + COLUMN_GUTTER;
+                result.push_str(&" ".repeat(padding));
+            }
+        }
+        result.push('\n');
+    }
+    result
+}
+
+/// Renders each top-level directory as a separate tree under a header with its name. Top-level
+/// files come first, as a tree of their own without a header.
+fn render_per_root(root: &Tree, options: &Options) -> String {
+    let (directories, files): (Tree, Tree) = root
+        .iter()
+        .map(|(name, node)| (name.clone(), node.clone()))
+        .partition(|(_, node)| node.children.is_some());
+    let mut sections = Vec::new();
+    if !files.is_empty() {
+        sections.push(render_entries(
+            format_tree_as_entries(&files, "", "", options),
+            options,
+        ));
+    }
+    for (name, node) in sorted_children(&directories, options) {
+        let header = if options.color {
+            name.blue().bold()
+        } else {
+            name.as_str().into()
+        };
+        let subtree = node.children.as_ref().unwrap();
+        let body = render_entries(format_tree_as_entries(subtree, "", name, options), options);
+        sections.push(format!("{}\n{}", header, body));
+    }
+    sections.join("\n")
+}
+
+/// Counts the characters of `s` that take up space on the terminal, skipping ANSI escape sequences.
+fn visible_width(s: &str) -> usize {
+    let mut width = 0;
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c == '\u{1b}' {
+            for c in chars.by_ref() {
+                if c.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+        } else {
+            width += 1;
+        }
+    }
+    width
+}
+
+/// The `ICONS` entry for the extension of `name`, if it has one.
+fn find_icon(name: &str) -> Option<(&'static str, &'static str)> {
+    let extension = Path::new(name)
+        .extension()?
+        .to_string_lossy()
+        .to_lowercase();
+    ICONS.iter().find(|(known, _)| *known == extension).copied()
+}
+
+/// Lists the `ICONS` of the extensions of the files in `root`, e.g. `\u{e7a8} .rs  \u{e620} .lua`,
+/// by extension. `None` when no file has one.
+fn render_icon_legend(root: &Tree) -> Option<String> {
+    fn collect(tree: &Tree, icons: &mut BTreeSet<(&'static str, &'static str)>) {
+        for (name, node) in tree {
+            match &node.children {
+                Some(children) => collect(children, icons),
+                None => icons.extend(find_icon(name)),
+            }
+        }
+    }
+    let mut icons = BTreeSet::new();
+    collect(root, &mut icons);
+    if icons.is_empty() {
+        return None;
+    }
+    let items: Vec<String> = icons
+        .into_iter()
+        .map(|(extension, icon)| format!("{} .{}", icon, extension))
+        .collect();
+    Some(items.join("  "))
+}
+
+/// Whether `name` has one of the extensions of `Options::highlight_extensions`.
+fn has_highlighted_extension(name: &str, options: &Options) -> bool {
+    let Some(extension) = Path::new(name).extension() else {
+        return false;
+    };
+    let extension = extension.to_string_lossy();
+    options
+        .highlight_extensions
+        .iter()
+        .any(|highlighted| highlighted.eq_ignore_ascii_case(&extension))
+}
+
+/// Colors `s` by where `timestamp` falls between the oldest and the newest of `range`.
+fn apply_age_color(s: &str, timestamp: u64, (oldest, newest): (u64, u64)) -> ColoredString {
+    let heat = if newest == oldest {
+        1.0
+    } else {
+        (timestamp - oldest) as f64 / (newest - oldest) as f64
+    };
+    let step = (heat * (AGE_GRADIENT.len() - 1) as f64).round() as usize;
+    s.color(AGE_GRADIENT[step])
+}
+
+/// Spells out a status such as `MM` for `Options::status_display`.
+fn format_status(status: &str, display: StatusDisplay) -> String {
+    let codes: Vec<char> = status.chars().collect();
+    match display {
+        StatusDisplay::Raw => status.to_string(),
+        StatusDisplay::Single => match codes[..] {
+            [x, y] if x == y && x.is_ascii_alphabetic() => x.to_string(),
+            _ => status.to_string(),
+        },
+        StatusDisplay::Verbose => match codes[..] {
+            ['?', '?'] => "untracked".to_string(),
+            ['!', '!'] => "ignored".to_string(),
+            _ if codes.contains(&'U') || status == "AA" || status == "DD" => "unmerged".to_string(),
+            [x, y] if x == y => format!("{} (staged+unstaged)", describe_status_code(x)),
+            [x, y] => format!(
+                "{} (staged), {} (unstaged)",
+                describe_status_code(x),
+                describe_status_code(y)
+            ),
+            _ => codes
+                .iter()
+                .map(|&code| describe_status_code(code))
+                .collect::<Vec<_>>()
+                .join(", "),
+        },
+    }
+}
+
+fn describe_status_code(code: char) -> &'static str {
+    match code {
+        'M' => "modified",
+        'T' => "type changed",
+        'A' => "added",
+        'D' => "deleted",
+        'R' => "renamed",
+        'C' => "copied",
+        'U' => "unmerged",
+        _ => "unknown",
+    }
+}
+
+/// Inserts `path` into `tree`, with `leaf` as the node for its last component. With
+/// `ignore_case`, a directory whose name only differs in case from an existing directory is
+/// merged into it, keeping the first-seen casing. Returns `false` if the path was dropped
+/// because a file and a directory would share a name.
+fn add_path_to_tree(tree: &mut Tree, path: &Path, leaf: Node, ignore_case: bool) -> bool {
+    let mut current_tree = tree;
+
+    let components: Vec<_> = path
+        .components()
+        .filter_map(|c| {
+            let name = c.as_os_str().to_string_lossy().into_owned();
+            if name == "/" || name.ends_with(":\\") {
+                None
+            } else {
+                Some(name)
+            }
+        })
+        .collect();
+
+    if components.is_empty() {
+        return true;
+    }
+
+    let last_index = components.len() - 1;
+    let mut leaf = Some(leaf);
+    for (i, mut component_name) in components.into_iter().enumerate() {
+        if i == last_index {
+            let leaf = leaf.take().unwrap();
+            match current_tree.entry(component_name) {
+                ordered_map::Entry::Vacant(entry) => {
+                    entry.insert(leaf);
+                }
+                // A directory listed after some of its contents keeps them but takes its status.
+                ordered_map::Entry::Occupied(existing) => {
+                    if existing.children.is_some() != leaf.children.is_some() {
+                        return false;
+                    }
+                    if existing.children.is_some() {
+                        existing.status = existing.status.take().or(leaf.status);
+                        existing.listed |= leaf.listed;
+                    }
+                }
+            }
+            continue;
+        }
+        if ignore_case {
+            if let Some(existing) = find_directory_ignoring_case(current_tree, &component_name) {
+                component_name = existing;
+            }
+        }
+        let entry = current_tree
+            .entry(component_name)
+            .or_insert_with(|| Node::new_directory(None));
+
+        if let Some(subtree) = &mut entry.children {
+            current_tree = subtree;
+        } else {
+            return false;
+        }
+    }
+    true
+}
+
+/// Builds a single tree out of two path listings, marking paths only in `after` as added (`A`)
+/// and paths only in `before` as deleted (`D`). Paths in both are left without a status.
+pub fn diff_trees(before: &[String], after: &[String]) -> Tree {
+    let before_set: HashSet<&str> = before.iter().map(String::as_str).collect();
+    let after_set: HashSet<&str> = after.iter().map(String::as_str).collect();
+
+    let mut tree = Tree::new();
+    for path in after {
+        let status = (!before_set.contains(path.as_str())).then(|| "A".to_string());
+        add_path_to_tree(
+            &mut tree,
+            Path::new(path),
+            Node::new_file(status, None),
+            false,
+        );
+    }
+    for path in before {
+        if !after_set.contains(path.as_str()) {
+            let leaf = Node::new_file(Some("D".to_string()), None);
+            add_path_to_tree(&mut tree, Path::new(path), leaf, false);
+        }
+    }
+    tree
+}
+
+fn find_directory_ignoring_case(tree: &Tree, name: &str) -> Option<String> {
+    let lowercase = name.to_lowercase();
+    tree.iter()
+        .find(|(key, node)| node.children.is_some() && key.to_lowercase() == lowercase)
+        .map(|(key, _)| key.clone())
+}
+
+/// Counts the changed entries (those with a status or line counts) below `tree`.
+fn count_changes(tree: &Tree) -> usize {
+    tree.values()
+        .map(|node| {
+            let own = usize::from(node.status.is_some() || node.numstat.is_some());
+            own + node.children.as_ref().map_or(0, count_changes)
+        })
+        .sum()
+}
+
+/// Whether `status` passes the `only` filter. A one-letter code also matches two-letter statuses
+/// with it in one column, so `M` keeps `MM` and `AM`, or in both of them with
+/// `ColumnMerge::And`, so `M` keeps `MM` only.
+fn matches_only(status: &str, options: &Options) -> bool {
+    options.only.is_empty()
+        || options.only.iter().any(|code| {
+            let mut letters = code.chars();
+            let (Some(letter), None) = (letters.next(), letters.next()) else {
+                return status == code;
+            };
+            status == code
+                || (status != "??"
+                    && match options.merge_status_columns {
+                        ColumnMerge::Or => status.contains(letter),
+                        ColumnMerge::And => {
+                            status.chars().count() == 2 && status.chars().all(|c| c == letter)
+                        }
+                    })
+        })
+}
+
+/// Counts the files, directories or both below `tree`, at any depth.
+fn count_entries(tree: &Tree, mode: CountMode) -> usize {
+    tree.values()
+        .map(|node| match &node.children {
+            Some(subtree) => usize::from(mode != CountMode::Files) + count_entries(subtree, mode),
+            None => usize::from(mode != CountMode::Dirs),
+        })
+        .sum()
+}
+
+/// Counts the entries of `mode` below `tree` with their noun, e.g. `3 files`.
+fn count_badge(tree: &Tree, mode: CountMode) -> String {
+    let count = count_entries(tree, mode);
+    let noun = match (mode, count) {
+        (CountMode::Files, 1) => "file",
+        (CountMode::Files, _) => "files",
+        (CountMode::Dirs, 1) => "dir",
+        (CountMode::Dirs, _) => "dirs",
+        (CountMode::All, 1) => "entry",
+        (CountMode::All, _) => "entries",
+    };
+    format!("{} {}", count, noun)
+}
+
+/// Whether `Options::collapse_pattern` matches the directory `name`, given as a possibly
+/// compacted name below `parent_path`, by its last component or its full path.
+fn is_collapsed(name: &str, parent_path: &str, options: &Options) -> bool {
+    options.collapse_pattern.as_ref().is_some_and(|pattern| {
+        let last = name.rsplit('/').next().unwrap_or(name);
+        pattern.is_match(last) || pattern.is_match(&join_path(parent_path, name))
+    })
+}
+
+/// Counts the directories `compact` would join into one entry starting at `node`. A directory
+/// with a status of its own ends the chain, so that the status stays on its line.
+fn compact_chain_length(mut node: &Node) -> usize {
+    let mut length = 0;
+    while let Some(children) = &node.children {
+        length += 1;
+        if node.status.is_some() {
+            break;
+        }
+        match children.values().next() {
+            Some(child) if children.len() == 1 && child.children.is_some() => node = child,
+            _ => break,
+        }
+    }
+    length
+}
+
+/// Orders the entries of one level for display.
+fn sorted_children<'a>(tree: &'a Tree, options: &Options) -> Vec<(&'a String, &'a Node)> {
+    let mut children: Vec<_> = tree.iter().collect();
+    match options.sort {
+        SortOrder::Name => children.sort_by_key(|&(name, _)| name),
+        SortOrder::Locale => children.sort_by(|(a, _), (b, _)| collate::compare(a, b)),
+        SortOrder::Input => {}
+    }
+    match options.sort_key {
+        SortKey::Name => {}
+        // `a.txt` comes before `a/b` in a sorted list of paths, but after the directory `a`.
+        SortKey::Path => children.sort_by_cached_key(|(name, node)| match node.children {
+            Some(_) => format!("{}/", name),
+            None => name.to_string(),
+        }),
+        SortKey::Ext => children.sort_by_cached_key(|(name, node)| match node.children {
+            Some(_) => None,
+            None => Path::new(name.as_str())
+                .extension()
+                .map(|extension| extension.to_string_lossy().to_lowercase()),
+        }),
+        SortKey::Status => children.sort_by_cached_key(|(_, node)| match &node.status {
+            Some(status) => status_rank(status, options),
+            None => node
+                .children
+                .as_ref()
+                .and_then(|subtree| dominant_status(subtree, options))
+                .map_or(usize::MAX, |status| status_rank(&status, options)),
+        }),
+    }
+    if options.reverse {
+        children.reverse();
+    }
+    if options.sort_dirs_by_count {
+        // Busiest directories first, files after them in their usual order.
+        children.sort_by_cached_key(|(_, node)| match &node.children {
+            Some(subtree) => (false, Reverse(count_changes(subtree))),
+            None => (true, Reverse(0)),
+        });
+    }
+    if options.dirs_first {
+        children.sort_by_key(|(_, node)| node.children.is_none());
+    }
+    if options.files_first {
+        children.sort_by_key(|(_, node)| node.children.is_some());
+    }
+    if options.untracked_last {
+        children.sort_by_key(|(_, node)| matches!(node.status.as_deref(), Some("??" | "!!")));
+    }
+    children
+}
+
+/// Where `status` comes in `status_priority`; lower is more important. A two-letter status
+/// like `AM` ranks as its more important letter, and unknown codes come after all listed ones.
+fn status_rank(status: &str, options: &Options) -> usize {
+    let priority: Vec<&str> = if options.status_priority.is_empty() {
+        DEFAULT_STATUS_PRIORITY.to_vec()
+    } else {
+        options.status_priority.iter().map(String::as_str).collect()
+    };
+    let position = |code: &str| priority.iter().position(|&p| p == code);
+    position(status)
+        .or_else(|| {
+            status
+                .char_indices()
+                .filter_map(|(i, c)| position(&status[i..i + c.len_utf8()]))
+                .min()
+        })
+        .unwrap_or(priority.len())
+}
+
+/// The most important status of anything below `tree`.
+fn dominant_status(tree: &Tree, options: &Options) -> Option<String> {
+    tree.values()
+        .flat_map(|node| {
+            let nested = node
+                .children
+                .as_ref()
+                .and_then(|c| dominant_status(c, options));
+            node.status.clone().into_iter().chain(nested)
+        })
+        .min_by_key(|status| status_rank(status, options))
+}
+
+fn join_path(parent_path: &str, name: &str) -> String {
+    if parent_path.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}/{}", parent_path, name)
+    }
+}
+
+/// Returns the submodule that `path` is, or lies inside of.
+fn find_submodule<'a>(path: &str, options: &'a Options) -> Option<&'a str> {
+    options
+        .submodules
+        .iter()
+        .map(|submodule| submodule.trim_end_matches('/'))
+        .find(|submodule| {
+            path.strip_prefix(submodule)
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with('/'))
+        })
+}
+
+fn is_current(path: &str, options: &Options) -> bool {
+    options
+        .current
+        .as_ref()
+        .is_some_and(|current| Path::new(current) == Path::new(path))
+}
+
+/// Splits `name` into pieces of at most `width` characters.
+fn wrap_name(name: &str, width: usize) -> Vec<String> {
+    let chars: Vec<char> = name.chars().collect();
+    chars
+        .chunks(width)
+        .map(|chunk| chunk.iter().collect())
+        .collect()
+}
+
+/// Recursively builds a vector of LineEntry structs representing the tree structure.
+/// `parent_path` is the slash-joined path of `tree` itself, empty at the root.
+fn format_tree_as_entries(
+    tree: &Tree,
+    prefix: &str,
+    parent_path: &str,
+    options: &Options,
+) -> Vec<LineEntry> {
+    format_subtree_entries(tree, &Rc::from(prefix), parent_path, options)
+}
+
+/// `format_tree_as_entries`, with each directory's prefix built once and shared by its lines.
+fn format_subtree_entries(
+    tree: &Tree,
+    prefix: &Rc<str>,
+    parent_path: &str,
+    options: &Options,
+) -> Vec<LineEntry> {
+    let mut entries = Vec::new();
+    let indent = |prefix: &Rc<str>| {
+        LineEntry::Indent(if options.unshared_prefixes {
+            Rc::from(&**prefix)
+        } else {
+            Rc::clone(prefix)
+        })
+    };
+    // With `bare_root`, top-level entries start at column 0 and their children are not indented.
+    let bare = options.bare_root && parent_path.is_empty();
+    let connectors = options.tree_chars.connectors();
+    let connector_for = |is_last: bool| match (bare, is_last) {
+        (true, _) => "",
+        (false, true) => connectors.last,
+        (false, false) => connectors.branch,
+    };
+    let mut children = sorted_children(tree, options);
+    let omitted = options
+        .max_siblings
+        .map(|limit| omit_siblings(&mut children, limit))
+        .unwrap_or_default();
+    let mut iter = children.into_iter().peekable();
+    while let Some((name, node)) = iter.next() {
+        let is_file = |name: &str, node: &Node| {
+            node.children.is_none()
+                && find_submodule(&join_path(parent_path, name), options).is_none()
+        };
+        if options.merge_files && is_file(name, node) {
+            // Keep adding the following files to this line while they fit.
+            let mut files = vec![(name, node)];
+            let mut width = visible_width(prefix) + connector_for(false).chars().count();
+            width += name.chars().count();
+            while let Some(&(next_name, next_node)) = iter.peek() {
+                width += 2 + next_name.chars().count();
+                if !is_file(next_name, next_node) || width > options.width.unwrap_or(DEFAULT_WIDTH)
+                {
+                    break;
+                }
+                files.push((next_name, next_node));
+                iter.next();
+            }
+            let is_last = iter.peek().is_none() && omitted.is_empty();
+            entries.push(indent(prefix));
+            if !bare {
+                entries.push(LineEntry::Connector(connector_for(is_last).to_string()));
+            }
+            for (i, (name, node)) in files.into_iter().enumerate() {
+                if i > 0 {
+                    entries.push(LineEntry::Connector(", ".to_string()));
+                }
+                let path = join_path(parent_path, name);
+                if options.status_glyphs {
+                    entries.push(LineEntry::StatusGlyph(node.status.clone()));
+                }
+                if options.icons {
+                    entries.push(LineEntry::Icon(
+                        find_icon(name).map_or(DEFAULT_ICON, |(_, icon)| icon),
+                    ));
+                }
+                entries.push(LineEntry::File {
+                    name: name.clone(),
+                    status: node.status.clone(),
+                    current: is_current(&path, options),
+                    timestamp: node.timestamp,
+                });
+                if options.show_depth {
+                    entries.push(LineEntry::Annotation(depth_label(&path)));
+                }
+                if let (Some(status), Some(display)) = (&node.status, options.status_display) {
+                    entries.push(LineEntry::Annotation(format_status(status, display)));
+                }
+                if let Some(label) = options.annotations.get(&path) {
+                    entries.push(LineEntry::Annotation(format!("[{}]", label)));
+                }
+            }
+            continue;
+        }
+
+        let mut compacted_name = name.clone();
+        let mut node_to_print = node;
+
+        if options.compact && compact_chain_length(node) >= options.compact_threshold.unwrap_or(0) {
+            while let Some(current_subtree) = &node_to_print.children {
+                // Joining past a directory with a status would leave nowhere to show it.
+                // So would joining past a collapsed one.
+                if current_subtree.len() != 1
+                    || node_to_print.status.is_some()
+                    || is_collapsed(&compacted_name, parent_path, options)
+                {
+                    break;
+                }
+                let (child_name, child_node) = current_subtree.iter().next().unwrap();
+                if child_node.children.is_some() {
+                    compacted_name.push('/');
+                    compacted_name.push_str(child_name);
+                    node_to_print = child_node;
+                    continue;
+                }
+                break;
+            }
+        }
+
+        let collapsed =
+            node_to_print.children.is_some() && is_collapsed(&compacted_name, parent_path, options);
+        let path = join_path(parent_path, &compacted_name);
+        if options.guides_only {
+            compacted_name = GUIDE_PLACEHOLDER.to_string();
+        }
+        let is_last = iter.peek().is_none() && omitted.is_empty();
+        let connector = connector_for(is_last);
+        // Only directories and wrapped names need it, so it is built on first use.
+        let child_prefix = OnceCell::new();
+        let child_prefix = || {
+            let prefix = child_prefix.get_or_init(|| -> Rc<str> {
+                if bare {
+                    return Rc::from("");
+                }
+                let guide = if is_last {
+                    connectors.blank
+                } else {
+                    connectors.vertical
+                };
+                Rc::from(format!("{}{}", prefix, guide))
+            });
+            Rc::clone(prefix)
+        };
+
+        if options.unshared_prefixes {
+            // Every entry used to build the prefix of its children, needed or not.
+            child_prefix();
+        }
+        entries.push(indent(prefix));
+        if !bare {
+            entries.push(LineEntry::Connector(connector.to_string()));
+        }
+        let mut continuation_entries = Vec::new();
+        let untracked_directory =
+            node_to_print.children.is_some() && node_to_print.status.as_deref() == Some("??");
+        let submodule =
+            node_to_print.children.is_none() && find_submodule(&path, options).is_some();
+        if options.status_glyphs && node_to_print.children.is_none() && !submodule {
+            entries.push(LineEntry::StatusGlyph(node_to_print.status.clone()));
+        }
+        if options.icons && node_to_print.children.is_none() && !submodule {
+            let icon = find_icon(&compacted_name).map_or(DEFAULT_ICON, |(_, icon)| icon);
+            entries.push(LineEntry::Icon(icon));
+        }
+        entries.push(if submodule {
+            LineEntry::Submodule {
+                name: compacted_name,
+                status: node_to_print.status.clone(),
+            }
+        } else if node_to_print.children.is_some() {
+            LineEntry::Directory {
+                name: match &options.compact_separator {
+                    Some(separator) => compacted_name.replace('/', separator),
+                    None => compacted_name,
+                },
+                status: node_to_print.status.clone(),
+                oversized: options.warn_dir_size.is_some_and(|limit| {
+                    node_to_print.children.as_ref().map_or(0, Tree::len) > limit
+                }),
+                dirty: options.highlight_dirty_path
+                    && node_to_print.children.as_ref().map_or(0, count_changes) > 0,
+            }
+        } else {
+            let current = is_current(&path, options);
+            let width = options.wrap.map_or(usize::MAX, |wrap| {
+                wrap.saturating_sub(visible_width(prefix) + connector.chars().count())
+                    .max(1)
+            });
+            let mut chunks = wrap_name(&compacted_name, width).into_iter();
+            let first = LineEntry::File {
+                name: chunks.next().unwrap_or_default(),
+                status: node_to_print.status.clone(),
+                current,
+                timestamp: node_to_print.timestamp,
+            };
+            // The rest of the name continues under its first character, keeping the guides.
+            for chunk in chunks {
+                continuation_entries.push(indent(&child_prefix()));
+                if options.status_glyphs {
+                    continuation_entries.push(LineEntry::StatusGlyph(None));
+                }
+                continuation_entries.push(LineEntry::File {
+                    name: chunk,
+                    status: node_to_print.status.clone(),
+                    current,
+                    timestamp: node_to_print.timestamp,
+                });
+            }
+            first
+        });
+        if options.show_depth {
+            entries.push(LineEntry::Annotation(depth_label(&path)));
+        }
+        entries.append(&mut continuation_entries);
+        if node_to_print.children.is_none() {
+            if let (Some(status), Some(display)) = (&node_to_print.status, options.status_display) {
+                entries.push(LineEntry::Annotation(format_status(status, display)));
+            }
+        }
+        let numstat = match &node_to_print.children {
+            Some(subtree) => aggregate_numstat(subtree),
+            None => node_to_print.numstat,
+        };
+        match numstat {
+            Some(numstat) if options.bars && node_to_print.children.is_none() => {
+                entries.push(LineEntry::Bar(numstat));
+            }
+            Some(numstat) => entries.push(LineEntry::Numstat(numstat)),
+            None => {}
+        }
+        if let Some(diffstat) = &node_to_print.diffstat {
+            entries.push(LineEntry::DiffStat(diffstat.clone()));
+        }
+        if untracked_directory {
+            entries.push(LineEntry::Annotation("(untracked dir)".to_string()));
+        }
+        if options.dir_status {
+            if let Some(status) = node_to_print
+                .children
+                .as_ref()
+                .and_then(|subtree| dominant_status(subtree, options))
+            {
+                entries.push(LineEntry::Annotation(format!("({})", status)));
+            }
+        }
+        if let Some(subtree) = &node_to_print.children {
+            // A collapsed directory always gets a badge, counting what `counts` would.
+            if collapsed {
+                let mode = options.counts.unwrap_or(CountMode::All);
+                entries.push(LineEntry::Annotation(format!(
+                    "(… {})",
+                    count_badge(subtree, mode)
+                )));
+            } else if let Some(mode) = options.counts {
+                entries.push(LineEntry::Annotation(format!(
+                    "({})",
+                    count_badge(subtree, mode)
+                )));
+            }
+        }
+        if submodule {
+            entries.push(LineEntry::Annotation("(submodule)".to_string()));
+        }
+        if node_to_print.children.is_none() {
+            if let Some(label) = options.annotations.get(&path) {
+                entries.push(LineEntry::Annotation(format!("[{}]", label)));
+            }
+        }
+
+        if let Some(subtree) = node_to_print.children.as_ref().filter(|_| !collapsed) {
+            let brackets = options.brackets && !subtree.is_empty();
+            if brackets {
+                entries.push(indent(&child_prefix()));
+                entries.push(LineEntry::Connector(connectors.open.to_string()));
+            }
+            entries.extend(format_subtree_entries(
+                subtree,
+                &child_prefix(),
+                &path,
+                options,
+            ));
+            if brackets {
+                entries.push(indent(&child_prefix()));
+                entries.push(LineEntry::Connector(connectors.close.to_string()));
+            }
+        }
+    }
+    for (i, summary) in omitted.iter().enumerate() {
+        entries.push(indent(prefix));
+        if !bare {
+            let is_last = i + 1 == omitted.len();
+            entries.push(LineEntry::Connector(connector_for(is_last).to_string()));
+        }
+        entries.push(LineEntry::Omitted(summary.clone()));
+    }
+    entries
+}
+
+/// `[d2]` for `Options::show_depth`, where top-level entries are at depth 1. A compacted
+/// directory is at the depth of its last component.
+fn depth_label(path: &str) -> String {
+    format!("[d{}]", path.split('/').count())
+}
+
+/// Keeps the first `limit` files and the first `limit` directories of `children`, returning
+/// lines like `+7 more files` for the ones it drops.
+fn omit_siblings(children: &mut Vec<(&String, &Node)>, limit: usize) -> Vec<String> {
+    let (mut files, mut dirs) = (0, 0);
+    children.retain(|(_, node)| {
+        let seen = if node.children.is_some() {
+            &mut dirs
+        } else {
+            &mut files
+        };
+        *seen += 1;
+        *seen <= limit
+    });
+    [(files, "file", "files"), (dirs, "dir", "dirs")]
+        .into_iter()
+        .filter(|&(count, _, _)| count > limit)
+        .map(|(count, one, many)| {
+            let rest = count - limit;
+            format!("+{} more {}", rest, if rest == 1 { one } else { many })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_paths_with_status(paths: &[&str]) -> Vec<PathEntry> {
+        paths.iter().map(|&s| PathEntry::new(s, "")).collect()
+    }
+
+    /// The neovim config that `test_generate_tree` renders.
+    fn nvim_paths() -> Vec<PathEntry> {
+        create_paths_with_status(&[
+            "nvim/after/lsp/tailwindcss.lua",
+            "nvim/after/lsp/ts_ls.lua",
+            "nvim/after/lsp/denols.lua",
+            "nvim/lazy-lock.json",
+            "nvim/lua/setup-lazynvim.lua",
+            "nvim/lua/install-lazynvim.lua",
+            "nvim/lua/options.lua",
+            "nvim/lua/plugins/ui.lua",
+            "nvim/lua/plugins/completion.lua",
+            "nvim/lua/plugins/treesitter.lua",
+            "nvim/lua/plugins/fuzzy-finder.lua",
+            "nvim/lua/plugins/colorscheme.lua",
+            "nvim/lua/plugins/manipulation.lua",
+            "nvim/lua/plugins/lsp.lua",
+            "nvim/lua/plugins/formatter.lua",
+            "nvim/lua/plugins/others.lua",
+            "nvim/lua/plugins/alpha-nvim.lua",
+            "nvim/lua/plugins/filer.lua",
+            "nvim/lua/use-extui.lua",
+            "nvim/lua/disable-providers.lua",
+            "nvim/lua/keymappings.lua",
+            "nvim/lua/easy-setup-autocmd/init.lua",
+            "nvim/lua/improve-default-scheme/init.lua",
+            "nvim/init.lua",
+            "nvim/ftplugin/qf.lua",
+        ])
+    }
+
+    #[test]
+    fn test_generate_tree() {
+        assert_eq!(
+            generate_tree_from_paths(
+                &nvim_paths(),
+                &Options {
+                    compact: false,
+                    color: false,
+                    ..Default::default()
+                }
+            ),
+            r#"└── nvim
+    ├── after
+    │   └── lsp
+    │       ├── denols.lua
+    │       ├── tailwindcss.lua
+    │       └── ts_ls.lua
+    ├── ftplugin
+    │   └── qf.lua
+    ├── init.lua
+    ├── lazy-lock.json
+    └── lua
+        ├── disable-providers.lua
+        ├── easy-setup-autocmd
+        │   └── init.lua
+        ├── improve-default-scheme
+        │   └── init.lua
+        ├── install-lazynvim.lua
+        ├── keymappings.lua
+        ├── options.lua
+        ├── plugins
+        │   ├── alpha-nvim.lua
+        │   ├── colorscheme.lua
+        │   ├── completion.lua
+        │   ├── filer.lua
+        │   ├── formatter.lua
+        │   ├── fuzzy-finder.lua
+        │   ├── lsp.lua
+        │   ├── manipulation.lua
+        │   ├── others.lua
+        │   ├── treesitter.lua
+        │   └── ui.lua
+        ├── setup-lazynvim.lua
+        └── use-extui.lua
+"#
+        );
+    }
+
+    #[test]
+    fn test_generate_tree_compact() {
+        assert_eq!(
+            generate_tree_from_paths(
+                &create_paths_with_status(&[
+                    "dotfiles/nvim/after/lsp/tailwindcss.lua",
+                    "dotfiles/nvim/after/lsp/ts_ls.lua",
+                    "dotfiles/nvim/after/lsp/denols.lua",
+                    "dotfiles/nvim/lazy-lock.json",
+                    "dotfiles/nvim/lua/setup-lazynvim.lua",
+                    "dotfiles/nvim/lua/install-lazynvim.lua",
+                    "dotfiles/nvim/lua/options.lua",
+                    "dotfiles/nvim/lua/plugins/ui.lua",
+                    "dotfiles/nvim/lua/plugins/completion.lua",
+                    "dotfiles/nvim/lua/plugins/treesitter.lua",
+                    "dotfiles/nvim/lua/plugins/fuzzy-finder.lua",
+                    "dotfiles/nvim/lua/plugins/colorscheme.lua",
+                    "dotfiles/nvim/lua/plugins/manipulation.lua",
+                    "dotfiles/nvim/lua/plugins/lsp.lua",
+                    "dotfiles/nvim/lua/plugins/formatter.lua",
+                    "dotfiles/nvim/lua/plugins/others.lua",
+                    "dotfiles/nvim/lua/plugins/alpha-nvim.lua",
+                    "dotfiles/nvim/lua/plugins/filer.lua",
+                    "dotfiles/nvim/lua/use-extui.lua",
+                    "dotfiles/nvim/lua/disable-providers.lua",
+                    "dotfiles/nvim/lua/keymappings.lua",
+                    "dotfiles/nvim/lua/easy-setup-autocmd/init.lua",
+                    "dotfiles/nvim/lua/improve-default-scheme/init.lua",
+                    "dotfiles/nvim/init.lua",
+                    "dotfiles/nvim/ftplugin/qf.lua"
+                ]),
+                &Options {
+                    compact: true,
+                    color: false,
+                    ..Default::default()
+                }
+            ),
+            r#"└── dotfiles/nvim
+    ├── after/lsp
+    │   ├── denols.lua
+    │   ├── tailwindcss.lua
+    │   └── ts_ls.lua
+    ├── ftplugin
+    │   └── qf.lua
+    ├── init.lua
+    ├── lazy-lock.json
+    └── lua
+        ├── disable-providers.lua
+        ├── easy-setup-autocmd
+        │   └── init.lua
+        ├── improve-default-scheme
+        │   └── init.lua
+        ├── install-lazynvim.lua
+        ├── keymappings.lua
+        ├── options.lua
+        ├── plugins
+        │   ├── alpha-nvim.lua
+        │   ├── colorscheme.lua
+        │   ├── completion.lua
+        │   ├── filer.lua
+        │   ├── formatter.lua
+        │   ├── fuzzy-finder.lua
+        │   ├── lsp.lua
+        │   ├── manipulation.lua
+        │   ├── others.lua
+        │   ├── treesitter.lua
+        │   └── ui.lua
+        ├── setup-lazynvim.lua
+        └── use-extui.lua
+"#
+        );
+    }
+
+    #[test]
+    fn test_format_tree_as_lines() {
+        let mut tree = Tree::new();
+        add_path_to_tree(
+            &mut tree,
+            Path::new("a/b"),
+            Node::new_file(Some("M".to_string()), None),
+            false,
+        );
+        add_path_to_tree(
+            &mut tree,
+            Path::new("a/c"),
+            Node::new_file(Some("A".to_string()), None),
+            false,
+        );
+
+        let lines = format_tree_as_entries(&tree, "", "", &Options::default());
+
+        assert_eq!(
+            lines,
+            vec![
+                LineEntry::Indent("".into()),
+                LineEntry::Connector("└── ".to_string()),
+                LineEntry::Directory {
+                    name: "a".to_string(),
+                    status: None,
+                    oversized: false,
+                    dirty: false,
+                },
+                LineEntry::Indent("    ".into()),
+                LineEntry::Connector("├── ".to_string()),
+                LineEntry::File {
+                    name: "b".to_string(),
+                    status: Some("M".to_string()),
+                    current: false,
+                    timestamp: None,
+                },
+                LineEntry::Indent("    ".into()),
+                LineEntry::Connector("└── ".to_string()),
+                LineEntry::File {
+                    name: "c".to_string(),
+                    status: Some("A".to_string()),
+                    current: false,
+                    timestamp: None,
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn test_generate_tree_with_color() {
+        colored::control::set_override(true);
+        let paths = vec![PathEntry::new("a/b", "M"), PathEntry::new("a/c", "A")];
+        let options = &Options {
+            compact: false,
+            color: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            generate_tree_from_paths(&paths, options),
+            "\u{1b}[90m\u{1b}[0m\u{1b}[90m└── \u{1b}[0m\u{1b}[34ma\u{1b}[0m\n\u{1b}[90m    \u{1b}[0m\u{1b}[90m├── \u{1b}[0m\u{1b}[33mb\u{1b}[0m\n\u{1b}[90m    \u{1b}[0m\u{1b}[90m└── \u{1b}[0m\u{1b}[32mc\u{1b}[0m\n"
+        );
+    }
+
+    #[test]
+    fn test_generate_tree_from_porcelain_output() {
+        colored::control::set_override(true);
+        let paths = vec![
+            PathEntry::new("src/main.rs", "M"),
+            PathEntry::new("src/tree_generator.rs", "M"),
+            PathEntry::new("new_file.txt", "A"),
+            PathEntry::new("deleted_file.txt", "D"),
+            PathEntry::new("renamed_file.txt", "R"),
+            PathEntry::new("copied_file.txt", "C"),
+            PathEntry::new("unmerged_file.txt", "U"),
+            PathEntry::new("untracked_file.txt", "??"),
+        ];
+        let options = &Options {
+            compact: false,
+            color: true,
+            ..Default::default()
+        };
+        let expected = "\u{1b}[90m\u{1b}[0m\u{1b}[90m├── \u{1b}[0m\u{1b}[35mcopied_file.txt\u{1b}[0m\n\u{1b}[90m\u{1b}[0m\u{1b}[90m├── \u{1b}[0m\u{1b}[31mdeleted_file.txt\u{1b}[0m\n\u{1b}[90m\u{1b}[0m\u{1b}[90m├── \u{1b}[0m\u{1b}[32mnew_file.txt\u{1b}[0m\n\u{1b}[90m\u{1b}[0m\u{1b}[90m├── \u{1b}[0m\u{1b}[36mrenamed_file.txt\u{1b}[0m\n\u{1b}[90m\u{1b}[0m\u{1b}[90m├── \u{1b}[0m\u{1b}[34msrc\u{1b}[0m\n\u{1b}[90m│   \u{1b}[0m\u{1b}[90m├── \u{1b}[0m\u{1b}[33mmain.rs\u{1b}[0m\n\u{1b}[90m│   \u{1b}[0m\u{1b}[90m└── \u{1b}[0m\u{1b}[33mtree_generator.rs\u{1b}[0m\n\u{1b}[90m\u{1b}[0m\u{1b}[90m├── \u{1b}[0m\u{1b}[1;31munmerged_file.txt\u{1b}[0m\n\u{1b}[90m\u{1b}[0m\u{1b}[90m└── \u{1b}[0m\u{1b}[90muntracked_file.txt\u{1b}[0m\n";
+
+        assert_eq!(generate_tree_from_paths(&paths, options), expected);
+    }
+
+    #[test]
+    fn test_generate_tree_in_columns() {
+        assert_eq!(
+            generate_tree_from_paths(
+                &create_paths_with_status(&["a/x", "b/y", "c", "d"]),
+                &Options {
+                    columns: Some(2),
+                    ..Default::default()
+                }
+            ),
+            r#"├── a        ├── c
+│   └── x    └── d
+└── b
+    └── y
+"#
+        );
+    }
+
+    #[test]
+    fn test_generate_tree_ignore_case_dedup() {
+        let paths = create_paths_with_status(&["Src/a.rs", "src/b.rs"]);
+        assert_eq!(
+            generate_tree_from_paths(
+                &paths,
+                &Options {
+                    ignore_case_dedup: true,
+                    ..Default::default()
+                }
+            ),
+            r#"└── Src
+    ├── a.rs
+    └── b.rs
+"#
+        );
+        assert_eq!(
+            generate_tree_from_paths(&paths, &Options::default()),
+            r#"├── Src
+│   └── a.rs
+└── src
+    └── b.rs
+"#
+        );
+    }
+
+    #[test]
+    fn test_generate_tree_with_current_file() {
+        colored::control::set_override(true);
+        let paths = vec![PathEntry::new("a/b", "M"), PathEntry::new("a/c", "")];
+        let options = &Options {
+            color: true,
+            current: Some("a/b".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            generate_tree_from_paths(&paths, options),
+            "\u{1b}[90m\u{1b}[0m\u{1b}[90m└── \u{1b}[0m\u{1b}[34ma\u{1b}[0m\n\u{1b}[90m    \u{1b}[0m\u{1b}[90m├── \u{1b}[0m\u{1b}[1;7;33mb\u{1b}[0m\n\u{1b}[90m    \u{1b}[0m\u{1b}[90m└── \u{1b}[0mc\n"
+        );
+    }
+
+    #[test]
+    fn test_generate_tree_guides_only() {
+        assert_eq!(
+            generate_tree_from_paths(
+                &create_paths_with_status(&["a/b/c", "a/d", "e"]),
+                &Options {
+                    guides_only: true,
+                    ..Default::default()
+                }
+            ),
+            r#"├── ...
+│   ├── ...
+│   │   └── ...
+│   └── ...
+└── ...
+"#
+        );
+    }
+
+    #[test]
+    fn test_generate_tree_with_untracked_directory() {
+        let paths = vec![
+            PathEntry::new("build/", "??"),
+            PathEntry::new("src/main.rs", "M"),
+        ];
+        assert_eq!(
+            generate_tree_from_paths(&paths, &Options::default()),
+            r#"├── build (untracked dir)
+└── src
+    └── main.rs
+"#
+        );
+
+        colored::control::set_override(true);
+        let options = &Options {
+            color: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            generate_tree_from_paths(&paths[..1], options),
+            "\u{1b}[90m\u{1b}[0m\u{1b}[90m└── \u{1b}[0m\u{1b}[90mbuild\u{1b}[0m \u{1b}[90m(untracked dir)\u{1b}[0m\n"
+        );
+    }
+
+    #[test]
+    fn test_generate_tree_sort_dirs_by_count() {
+        let paths = vec![
+            PathEntry::new("README.md", "M"),
+            PathEntry::new("docs/guide.md", "M"),
+            PathEntry::new("src/main.rs", "M"),
+            PathEntry::new("src/parser.rs", "A"),
+            PathEntry::new("src/lib/tree.rs", "M"),
+        ];
+        assert_eq!(
+            generate_tree_from_paths(
+                &paths,
+                &Options {
+                    sort_dirs_by_count: true,
+                    ..Default::default()
+                }
+            ),
+            r#"├── src
+│   ├── lib
+│   │   └── tree.rs
+│   ├── main.rs
+│   └── parser.rs
+├── docs
+│   └── guide.md
+└── README.md
+"#
+        );
+    }
+
+    fn paths(paths: &[&str]) -> Vec<String> {
+        paths.iter().map(|&s| s.to_string()).collect()
+    }
+
+    fn file(status: Option<&str>) -> Node {
+        Node::new_file(status.map(str::to_string), None)
+    }
+
+    fn directory(children: Vec<(&str, Node)>) -> Node {
+        Node {
+            children: Some(
+                children
+                    .into_iter()
+                    .map(|(name, node)| (name.to_string(), node))
+                    .collect(),
+            ),
+            ..Node::new_directory(None)
+        }
+    }
+
+    #[test]
+    fn test_diff_trees_added_only() {
+        assert_eq!(
+            diff_trees(
+                &paths(&["src/main.rs"]),
+                &paths(&["src/main.rs", "src/lib.rs"])
+            ),
+            Tree::from([(
+                "src".to_string(),
+                directory(vec![("lib.rs", file(Some("A"))), ("main.rs", file(None))])
+            )])
+        );
+    }
+
+    #[test]
+    fn test_diff_trees_deleted_only() {
+        assert_eq!(
+            diff_trees(&paths(&["README.md", "old.txt"]), &paths(&["README.md"])),
+            Tree::from([
+                ("README.md".to_string(), file(None)),
+                ("old.txt".to_string(), file(Some("D"))),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_diff_trees_mixed() {
+        assert_eq!(
+            diff_trees(
+                &paths(&["src/a.rs", "src/b.rs", "docs/old.md"]),
+                &paths(&["src/b.rs", "src/c.rs", "docs/new.md"])
+            ),
+            Tree::from([
+                (
+                    "docs".to_string(),
+                    directory(vec![
+                        ("new.md", file(Some("A"))),
+                        ("old.md", file(Some("D")))
+                    ])
+                ),
+                (
+                    "src".to_string(),
+                    directory(vec![
+                        ("a.rs", file(Some("D"))),
+                        ("b.rs", file(None)),
+                        ("c.rs", file(Some("A"))),
+                    ])
+                ),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_generate_flat_paths() {
+        let paths = create_paths_with_status(&["src/main.rs", "README.md", "src/lib/tree.rs"]);
+        assert_eq!(
+            generate_tree_from_paths(
+                &paths,
+                &Options {
+                    flat: true,
+                    ..Default::default()
+                }
+            ),
+            "README.md\nsrc/lib/tree.rs\nsrc/main.rs\n"
+        );
+        assert_eq!(
+            generate_tree_from_paths(
+                &paths,
+                &Options {
+                    flat: true,
+                    print0: true,
+                    ..Default::default()
+                }
+            ),
+            "README.md\0src/lib/tree.rs\0src/main.rs"
+        );
+    }
+
+    #[test]
+    fn test_generate_tree_warn_dir_size() {
+        colored::control::set_override(true);
+        let paths = create_paths_with_status(&["big/a", "big/b", "big/c", "small/a"]);
+        let options = &Options {
+            color: true,
+            warn_dir_size: Some(2),
+            ..Default::default()
+        };
+        let output = generate_tree_from_paths(&paths, options);
+        assert!(output.contains("\u{1b}[91mbig\u{1b}[0m"));
+        assert!(output.contains("\u{1b}[34msmall\u{1b}[0m"));
+    }
+
+    #[cfg(feature = "tui")]
+    #[test]
+    fn test_to_spans() {
+        let paths = vec![PathEntry::new("a/b", "M")];
+        let options = &Options {
+            color: true,
+            ..Default::default()
+        };
+        let connector = Style {
+            fg: Some(Color::BrightBlack),
+            ..Default::default()
+        };
+        assert_eq!(
+            to_spans(&paths, options),
+            vec![
+                vec![
+                    ("".to_string(), connector),
+                    ("└── ".to_string(), connector),
+                    (
+                        "a".to_string(),
+                        Style {
+                            fg: Some(Color::Blue),
+                            ..Default::default()
+                        }
+                    ),
+                ],
+                vec![
+                    ("    ".to_string(), connector),
+                    ("└── ".to_string(), connector),
+                    (
+                        "b".to_string(),
+                        Style {
+                            fg: Some(Color::Yellow),
+                            ..Default::default()
+                        }
+                    ),
+                ],
+            ]
+        );
+    }
+
+    #[test]
+    fn test_generate_tree_trim_common_prefix() {
+        let options = &Options {
+            trim_common_prefix: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            generate_tree_from_paths(
+                &create_paths_with_status(&["a/b/c/d.txt", "a/b/e.txt"]),
+                options
+            ),
+            r#"├── c
+│   └── d.txt
+└── e.txt
+"#
+        );
+        assert_eq!(
+            generate_tree_from_paths(&create_paths_with_status(&["a/x", "b/y"]), options),
+            r#"├── a
+│   └── x
+└── b
+    └── y
+"#
+        );
+    }
+
+    #[test]
+    fn test_generate_tree_as_json() {
+        let paths = vec![
+            PathEntry::new("src/a\"b\\c", "M"),
+            PathEntry::new("tab\there", ""),
+            PathEntry::new("build/", "??"),
+        ];
+        let output = generate_tree_from_paths(
+            &paths,
+            &Options {
+                json: true,
+                ..Default::default()
+            },
+        );
+        assert_eq!(
+            output,
+            r#"[{"name":"build","type":"directory","status":"??","children":[]},{"name":"src","type":"directory","children":[{"name":"a\"b\\c","type":"file","status":"M"}]},{"name":"tab\there","type":"file"}]
+"#
+        );
+
+        let json::Value::Array(nodes) = json::parse(&output).unwrap() else {
+            panic!("expected an array");
+        };
+        let json::Value::Object(src) = &nodes[1] else {
+            panic!("expected an object");
+        };
+        let json::Value::Array(children) = &src[2].1 else {
+            panic!("expected an array");
+        };
+        let json::Value::Object(file) = &children[0] else {
+            panic!("expected an object");
+        };
+        assert_eq!(file[0].1, json::Value::String("a\"b\\c".to_string()));
+        let json::Value::Object(tab) = &nodes[2] else {
+            panic!("expected an object");
+        };
+        assert_eq!(tab[0].1, json::Value::String("tab\there".to_string()));
+    }
+
+    #[test]
+    fn test_generate_tree_wrap() {
+        assert_eq!(
+            generate_tree_from_paths(
+                &create_paths_with_status(&["d/verylongfilename.txt", "d/x", "e"]),
+                &Options {
+                    wrap: Some(16),
+                    ..Default::default()
+                }
+            ),
+            r#"├── d
+│   ├── verylong
+│   │   filename
+│   │   .txt
+│   └── x
+└── e
+"#
+        );
+    }
+
+    #[test]
+    fn test_generate_tree_merge_files() {
+        assert_eq!(
+            generate_tree_from_paths(
+                &create_paths_with_status(&["d/a.rs", "d/b.rs", "d/c.rs", "d/e.rs"]),
+                &Options {
+                    merge_files: true,
+                    width: Some(24),
+                    ..Default::default()
+                }
+            ),
+            r#"└── d
+    ├── a.rs, b.rs, c.rs
+    └── e.rs
+"#
+        );
+    }
+
+    #[test]
+    fn test_generate_tree_mixed_separators() {
+        let paths = create_paths_with_status(&["a/b\\c.txt", "a\\d.txt"]);
+        assert_eq!(
+            generate_tree_from_paths(
+                &paths,
+                &Options {
+                    mixed_separators: true,
+                    ..Default::default()
+                }
+            ),
+            r#"└── a
+    ├── b
+    │   └── c.txt
+    └── d.txt
+"#
+        );
+    }
+
+    #[test]
+    fn test_generate_tree_bare_root() {
+        assert_eq!(
+            generate_tree_from_paths(
+                &create_paths_with_status(&["src/main.rs", "src/lib/tree.rs", "README.md"]),
+                &Options {
+                    bare_root: true,
+                    ..Default::default()
+                }
+            ),
+            r#"README.md
+src
+├── lib
+│   └── tree.rs
+└── main.rs
+"#
+        );
+    }
+
+    #[test]
+    fn test_generate_tree_submodules() {
+        let paths = [
+            PathEntry::new("vendor/lib", "M"),
+            PathEntry::new("vendor/lib/src/a.c", "M"),
+            PathEntry::new("vendor/lib/build.log", "??"),
+            PathEntry::new("src/main.rs", "M"),
+        ];
+        let options = Options {
+            submodules: vec!["vendor/lib".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(
+            generate_tree_from_paths(&paths, &options),
+            r#"├── src
+│   └── main.rs
+└── vendor
+    └── lib (submodule)
+"#
+        );
+
+        colored::control::set_override(true);
+        let options = Options {
+            color: true,
+            ..options
+        };
+        assert_eq!(
+            generate_tree_from_paths(&[PathEntry::new("vendor/lib/src/a.c", "")], &options),
+            "\u{1b}[90m\u{1b}[0m\u{1b}[90m└── \u{1b}[0m\u{1b}[34mvendor\u{1b}[0m\n\u{1b}[90m    \u{1b}[0m\u{1b}[90m└── \u{1b}[0m\u{1b}[96mlib\u{1b}[0m \u{1b}[90m(submodule)\u{1b}[0m\n"
+        );
+    }
+
+    #[test]
+    fn test_generate_tree_per_root() {
+        assert_eq!(
+            generate_tree_from_paths(
+                &create_paths_with_status(&[
+                    "frontend/src/app.ts",
+                    "frontend/package.json",
+                    "backend/main.go",
+                    "README.md",
+                ]),
+                &Options {
+                    per_root: true,
+                    ..Default::default()
+                }
+            ),
+            r#"└── README.md
+
+backend
+└── main.go
+
+frontend
+├── package.json
+└── src
+    └── app.ts
+"#
+        );
+    }
+
+    #[test]
+    fn test_color_test() {
+        colored::control::set_override(true);
+        let output = color_test(&Options {
+            color: true,
+            ..Default::default()
+        });
+        for (style, name) in [
+            ("33", "modified.rs"),
+            ("32", "added.rs"),
+            ("31", "deleted.rs"),
+            ("36", "renamed.rs"),
+            ("35", "copied.rs"),
+            ("1;31", "conflicted.rs"),
+            ("90", "untracked.rs"),
+            ("90", "build"),
+            ("34", "src"),
+            ("90", "├── "),
+        ] {
+            let expected = format!("\u{1b}[{}m{}\u{1b}[0m", style, name);
+            assert!(
+                output.contains(&expected),
+                "{:?} not in {:?}",
+                expected,
+                output
+            );
+        }
+    }
+
+    #[test]
+    fn test_generate_tree_dirs_first_and_files_first() {
+        let paths = create_paths_with_status(&["b.txt", "a/x.txt", "c/y.txt", "d.txt"]);
+        assert_eq!(
+            generate_tree_from_paths(
+                &paths,
+                &Options {
+                    dirs_first: true,
+                    ..Default::default()
+                }
+            ),
+            r#"├── a
+│   └── x.txt
+├── c
+│   └── y.txt
+├── b.txt
+└── d.txt
+"#
+        );
+        assert_eq!(
+            generate_tree_from_paths(
+                &paths,
+                &Options {
+                    files_first: true,
+                    ..Default::default()
+                }
+            ),
+            r#"├── b.txt
+├── d.txt
+├── a
+│   └── x.txt
+└── c
+    └── y.txt
+"#
+        );
+    }
+
+    #[test]
+    fn test_generate_tree_age_gradient() {
+        let paths: Vec<PathEntry> = [("new.rs", 3000), ("mid.rs", 2000), ("old.rs", 1000)]
+            .into_iter()
+            .map(|(path, timestamp)| PathEntry {
+                timestamp: Some(timestamp),
+                ..PathEntry::new(path, "M")
+            })
+            .collect();
+        colored::control::set_override(true);
+        assert_eq!(
+            generate_tree_from_paths(
+                &paths,
+                &Options {
+                    color: true,
+                    age_gradient: true,
+                    ..Default::default()
+                }
+            ),
+            "\u{1b}[90m\u{1b}[0m\u{1b}[90m├── \u{1b}[0m\u{1b}[33mmid.rs\u{1b}[0m\n\
+             \u{1b}[90m\u{1b}[0m\u{1b}[90m├── \u{1b}[0m\u{1b}[91mnew.rs\u{1b}[0m\n\
+             \u{1b}[90m\u{1b}[0m\u{1b}[90m└── \u{1b}[0m\u{1b}[90mold.rs\u{1b}[0m\n"
+        );
+    }
+
+    #[test]
+    fn test_generate_tree_annotations() {
+        let options = Options {
+            annotations: HashMap::from([("src/main.rs".to_string(), "needs tests".to_string())]),
+            ..Default::default()
+        };
+        assert_eq!(
+            generate_tree_from_paths(
+                &create_paths_with_status(&["src/main.rs", "src/lib.rs", "main.rs"]),
+                &options
+            ),
+            r#"├── main.rs
+└── src
+    ├── lib.rs
+    └── main.rs [needs tests]
+"#
+        );
+    }
+
+    #[test]
+    fn test_generate_tree_uses_basic_sgr_codes() {
+        // `less -R` only passes through plain SGR sequences like `ESC[1;31m`.
+        colored::control::set_override(true);
+        let mut paths = create_paths_with_status(&["big/a", "big/b", "big/c", "src/main.rs"]);
+        paths.extend(
+            ["M", "A", "D", "R", "C", "U", "??"]
+                .into_iter()
+                .enumerate()
+                .map(|(i, status)| PathEntry {
+                    timestamp: Some(i as u64),
+                    numstat: Some(Numstat {
+                        added: Some(1),
+                        deleted: Some(1),
+                    }),
+                    ..PathEntry::new(&format!("files/{}", i), status)
+                }),
+        );
+        paths.push(PathEntry::new("build/", "??"));
+        for age_gradient in [false, true] {
+            let output = generate_tree_from_paths(
+                &paths,
+                &Options {
+                    color: true,
+                    current: Some("src/main.rs".to_string()),
+                    warn_dir_size: Some(2),
+                    age_gradient,
+                    ..Default::default()
+                },
+            );
+            for sequence in output.split('\u{1b}').skip(1) {
+                let params = &sequence[1..sequence.find('m').unwrap()];
+                assert!(sequence.starts_with('['), "{:?}", sequence);
+                assert!(
+                    params
+                        .split(';')
+                        .all(|code| matches!(code.parse(), Ok(0..=9 | 30..=37 | 90..=97))),
+                    "{:?}",
+                    params
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_generate_tree_status_display() {
+        let paths = [
+            PathEntry::new("both.rs", "MM"),
+            PathEntry::new("mixed.rs", "AM"),
+            PathEntry::new("new.rs", "??"),
+        ];
+        let render = |display| {
+            generate_tree_from_paths(
+                &paths,
+                &Options {
+                    status_display: Some(display),
+                    ..Default::default()
+                },
+            )
+        };
+        assert_eq!(
+            render(StatusDisplay::Raw),
+            r#"├── both.rs MM
+├── mixed.rs AM
+└── new.rs ??
+"#
+        );
+        assert_eq!(
+            render(StatusDisplay::Single),
+            r#"├── both.rs M
+├── mixed.rs AM
+└── new.rs ??
+"#
+        );
+        assert_eq!(
+            render(StatusDisplay::Verbose),
+            r#"├── both.rs modified (staged+unstaged)
+├── mixed.rs added (staged), modified (unstaged)
+└── new.rs untracked
+"#
+        );
+    }
+
+    #[test]
+    fn test_generate_tree_as_json_lines() {
+        let paths = [
+            PathEntry::new("src/main.rs", "M"),
+            PathEntry::new("README.md", ""),
+            PathEntry::new("build/", "??"),
+        ];
+        let options = Options {
+            jsonl: true,
+            ..Default::default()
+        };
+        let output = generate_tree_from_paths(&paths, &options);
+        assert_eq!(
+            output,
+            r#"{"path":"README.md","status":null,"depth":1}
+{"path":"build/","status":"??","depth":1}
+{"path":"src/main.rs","status":"M","depth":2}
+"#
+        );
+        for line in output.lines() {
+            assert!(matches!(json::parse(line), Ok(json::Value::Object(_))));
+        }
+
+        let mut streamed = Vec::new();
+        write_tree_as_json_lines(&mut streamed, &paths, &options).unwrap();
+        assert_eq!(String::from_utf8(streamed).unwrap(), output);
+    }
+
+    #[test]
+    fn test_generate_tree_highlight_dirty_path() {
+        colored::control::set_override(true);
+        let paths = [
+            PathEntry::new("docs/guide.md", ""),
+            PathEntry::new("src/lib/tree.rs", "M"),
+        ];
+        assert_eq!(
+            generate_tree_from_paths(
+                &paths,
+                &Options {
+                    color: true,
+                    highlight_dirty_path: true,
+                    ..Default::default()
+                }
+            ),
+            "\u{1b}[90m\u{1b}[0m\u{1b}[90m├── \u{1b}[0m\u{1b}[90mdocs\u{1b}[0m\n\
+             \u{1b}[90m│   \u{1b}[0m\u{1b}[90m└── \u{1b}[0mguide.md\n\
+             \u{1b}[90m\u{1b}[0m\u{1b}[90m└── \u{1b}[0m\u{1b}[1;94msrc\u{1b}[0m\n\
+             \u{1b}[90m    \u{1b}[0m\u{1b}[90m└── \u{1b}[0m\u{1b}[1;94mlib\u{1b}[0m\n\
+             \u{1b}[90m        \u{1b}[0m\u{1b}[90m└── \u{1b}[0m\u{1b}[33mtree.rs\u{1b}[0m\n"
+        );
+    }
+
+    #[test]
+    fn test_generate_tree_with_format() {
+        let paths = [
+            PathEntry::new("src/main.rs", "M"),
+            PathEntry::new("README.md", ""),
+        ];
+        let render = |template: &str| {
+            generate_tree_from_paths(
+                &paths,
+                &Options {
+                    format: Some(template.parse().unwrap()),
+                    ..Default::default()
+                },
+            )
+        };
+        assert_eq!(render("{status}\\t{path}"), "\tREADME.md\nM\tsrc/main.rs\n");
+        assert_eq!(render("{depth} {name}"), "1 README.md\n2 main.rs\n");
+    }
+
+    #[test]
+    fn test_generate_tree_min_depth() {
+        assert_eq!(
+            generate_tree_from_paths(
+                &create_paths_with_status(&[
+                    "nvim/after/lsp/ts_ls.lua",
+                    "nvim/lazy-lock.json",
+                    "nvim/lua/options.lua",
+                    "nvim/lua/plugins/ui.lua",
+                    "nvim/init.lua",
+                    "README.md",
+                ]),
+                &Options {
+                    min_depth: Some(2),
+                    ..Default::default()
+                }
+            ),
+            r#"├── nvim/after
+│   └── lsp
+│       └── ts_ls.lua
+├── nvim/init.lua
+├── nvim/lazy-lock.json
+└── nvim/lua
+    ├── options.lua
+    └── plugins
+        └── ui.lua
+"#
+        );
+    }
+
+    #[test]
+    fn test_tree_round_trips_through_json() {
+        let mut paths = create_paths_with_status(&["src/lib/a \"quoted\".rs", "README.md"]);
+        paths.extend([
+            PathEntry::new("build/", "??"),
+            PathEntry::new("docs/", "A"),
+            PathEntry {
+                numstat: Some(Numstat {
+                    added: Some(3),
+                    deleted: None,
+                }),
+                timestamp: Some(1700000000),
+                ..PathEntry::new("docs/guide.md", "M")
+            },
+            PathEntry {
+                diffstat: Some(DiffStat {
+                    changes: Some(4),
+                    bar: "++--".to_string(),
+                }),
+                ..PathEntry::new("src/main.rs", "M")
+            },
+        ]);
+        let tree = build_tree(&paths, &Options::default());
+        assert_eq!(tree_from_json(&tree_to_json(&tree)), Ok(tree));
+        assert_eq!(
+            tree_from_json(r#"[{"name":"a","type":"file","added":-1}]"#),
+            Err("expected a count".to_string())
+        );
+    }
+
+    #[test]
+    fn test_tree_round_trips_through_flat_list() {
+        let mut paths = create_paths_with_status(&["src/main.rs", "src/lib/tree.rs", "README.md"]);
+        paths.push(PathEntry::new("build/", "??"));
+        paths.push(PathEntry::new("src/", "A"));
+        let tree = build_tree(&paths, &Options::default());
+        let flat = tree_to_flat(&tree);
+        assert_eq!(
+            flat,
+            vec![
+                ("README.md".to_string(), None),
+                ("build/".to_string(), Some("??".to_string())),
+                ("src/".to_string(), Some("A".to_string())),
+                ("src/lib/tree.rs".to_string(), None),
+                ("src/main.rs".to_string(), None),
+            ]
+        );
+        assert_eq!(tree_from_flat(&flat), tree);
+    }
+
+    #[test]
+    fn test_generate_tree_flatten_single() {
+        let paths = create_paths_with_status(&["a/b/c/file.rs", "a/b/c/other.rs", "d.rs"]);
+        assert_eq!(
+            generate_tree_from_paths(
+                &paths,
+                &Options {
+                    flatten_single: true,
+                    ..Default::default()
+                }
+            ),
+            r#"├── c
+│   ├── file.rs
+│   └── other.rs
+└── d.rs
+"#
+        );
+        assert_eq!(
+            generate_tree_from_paths(
+                &paths,
+                &Options {
+                    compact: true,
+                    ..Default::default()
+                }
+            ),
+            r#"├── a/b/c
+│   ├── file.rs
+│   └── other.rs
+└── d.rs
+"#
+        );
+    }
+
+    #[test]
+    fn test_generate_tree_as_interactive_html() {
+        let paths = [
+            PathEntry::new("src/lib/<tree>.rs", "A"),
+            PathEntry::new("src/main.rs", "M"),
+            PathEntry::new("notes & todo.txt", "??"),
+        ];
+        let output = generate_tree_from_paths(
+            &paths,
+            &Options {
+                html_interactive: true,
+                ..Default::default()
+            },
+        );
+        let body = output
+            .strip_prefix(html::PAGE_START)
+            .and_then(|rest| rest.strip_suffix(html::PAGE_END))
+            .unwrap();
+        assert_eq!(
+            body,
+            r#"<ul>
+<li><span class="untracked">notes &amp; todo.txt</span></li>
+<li><details open><summary>src</summary>
+<ul>
+<li><details open><summary>lib</summary>
+<ul>
+<li><span class="added">&lt;tree&gt;.rs</span></li>
+</ul>
+</details></li>
+<li><span class="modified">main.rs</span></li>
+</ul>
+</details></li>
+</ul>
+"#
+        );
+    }
+
+    #[test]
+    fn test_generate_tree_status_priority() {
+        let paths = [
+            PathEntry::new("a.rs", "M"),
+            PathEntry::new("b.rs", "A"),
+            PathEntry::new("c.rs", "??"),
+            PathEntry::new("src/x.rs", "M"),
+            PathEntry::new("src/y.rs", "D"),
+        ];
+        let render = |status_priority: &[&str]| {
+            generate_tree_from_paths(
+                &paths,
+                &Options {
+                    sort_key: SortKey::Status,
+                    dir_status: true,
+                    status_priority: status_priority.iter().map(|s| s.to_string()).collect(),
+                    ..Default::default()
+                },
+            )
+        };
+        assert_eq!(
+            render(&[]),
+            r#"├── src (D)
+│   ├── y.rs
+│   └── x.rs
+├── b.rs
+├── a.rs
+└── c.rs
+"#
+        );
+        assert_eq!(
+            render(&["??", "M", "A"]),
+            r#"├── c.rs
+├── a.rs
+├── src (M)
+│   ├── x.rs
+│   └── y.rs
+└── b.rs
+"#
+        );
+    }
+
+    #[test]
+    fn test_generate_tree_fold_extensions() {
+        assert_eq!(
+            generate_tree_from_paths(
+                &create_paths_with_status(&[
+                    "nvim/init.lua",
+                    "nvim/lazy-lock.json",
                     "nvim/lua/plugins/ui.lua",
                     "nvim/lua/plugins/completion.lua",
                     "nvim/lua/plugins/treesitter.lua",
-                    "nvim/lua/plugins/fuzzy-finder.lua",
-                    "nvim/lua/plugins/colorscheme.lua",
-                    "nvim/lua/plugins/manipulation.lua",
                     "nvim/lua/plugins/lsp.lua",
-                    "nvim/lua/plugins/formatter.lua",
-                    "nvim/lua/plugins/others.lua",
-                    "nvim/lua/plugins/alpha-nvim.lua",
-                    "nvim/lua/plugins/filer.lua",
-                    "nvim/lua/use-extui.lua",
-                    "nvim/lua/disable-providers.lua",
-                    "nvim/lua/keymappings.lua",
-                    "nvim/lua/easy-setup-autocmd/init.lua",
-                    "nvim/lua/improve-default-scheme/init.lua",
-                    "nvim/init.lua",
-                    "nvim/ftplugin/qf.lua"
+                    "nvim/lua/plugins/README.md",
                 ]),
                 &Options {
-                    compact: false,
-                    color: false
+                    fold_extensions: true,
+                    ..Default::default()
+                }
+            ),
+            r#"└── nvim
+    ├── init.lua
+    ├── lazy-lock.json
+    └── lua
+        └── plugins
+            ├── *.lua (4)
+            └── README.md
+"#
+        );
+    }
+
+    #[test]
+    fn test_fold_extensions_keeps_statuses() {
+        let paths = [
+            PathEntry::new("a/b/c/", "??"),
+            PathEntry::new("a/b/c/x.rs", "M"),
+            PathEntry::new("a/b/c/y.rs", "D"),
+            PathEntry::new("a/b/c/z.rs", ""),
+        ];
+        let options = Options {
+            compact: true,
+            fold_extensions: true,
+            status_display: Some(StatusDisplay::Raw),
+            ..Default::default()
+        };
+        assert_eq!(
+            generate_tree_from_paths(&paths, &options),
+            "└── a/b/c (untracked dir)\n    └── *.rs (3) D\n"
+        );
+        colored::control::set_override(true);
+        let colored = generate_tree_from_paths(
+            &paths,
+            &Options {
+                color: true,
+                ..options
+            },
+        );
+        assert!(colored.contains("\u{1b}[90ma/b/c\u{1b}[0m"));
+        assert!(colored.contains("\u{1b}[31m*.rs (3)\u{1b}[0m"));
+    }
+
+    #[test]
+    fn test_find_path_conflicts() {
+        let paths = create_paths_with_status(&["a", "a/b", "c/d", "c", "e/", "e/f"]);
+        assert_eq!(
+            generate_tree_from_paths(&paths, &Options::default()),
+            r#"├── a
+├── c
+│   └── d
+└── e
+    └── f
+"#
+        );
+        let conflicts = find_path_conflicts(&paths, &Options::default());
+        assert_eq!(conflicts.len(), 2);
+        assert_eq!(
+            conflicts[0].to_string(),
+            "`a/b` conflicts with a file or directory of the same name and was left out"
+        );
+        assert_eq!(conflicts[1].path, "c");
+    }
+
+    #[test]
+    fn test_generate_tree_locale_sort() {
+        let paths = create_paths_with_status(&["Zebra.md", "école/a", "eagle.md", "Ética.md"]);
+        assert_eq!(
+            generate_tree_from_paths(&paths, &Options::default()),
+            r#"├── Zebra.md
+├── eagle.md
+├── Ética.md
+└── école
+    └── a
+"#
+        );
+        assert_eq!(
+            generate_tree_from_paths(
+                &paths,
+                &Options {
+                    sort: SortOrder::Locale,
+                    ..Default::default()
+                }
+            ),
+            r#"├── eagle.md
+├── école
+│   └── a
+├── Ética.md
+└── Zebra.md
+"#
+        );
+    }
+
+    #[test]
+    fn test_generate_tree_compact_threshold() {
+        assert_eq!(
+            generate_tree_from_paths(
+                &create_paths_with_status(&["x/y/file.rs", "p/q/r/s/file.rs"]),
+                &Options {
+                    compact: true,
+                    compact_threshold: Some(3),
+                    ..Default::default()
+                }
+            ),
+            r#"├── p/q/r/s
+│   └── file.rs
+└── x
+    └── y
+        └── file.rs
+"#
+        );
+    }
+
+    #[test]
+    fn test_render_built_tree() {
+        let mut tree = Tree::new();
+        tree.insert(
+            "src".to_string(),
+            Node {
+                children: Some(Tree::from([(
+                    "main.rs".to_string(),
+                    Node::new_file(Some("M".to_string()), None),
+                )])),
+                ..Node::new_directory(None)
+            },
+        );
+        tree.insert(
+            "notes".to_string(),
+            Node::new_directory(Some("??".to_string())),
+        );
+        assert_eq!(
+            render(&tree, &Options::default()),
+            r#"├── notes (untracked dir)
+└── src
+    └── main.rs
+"#
+        );
+
+        let mut tree = build_tree(
+            &create_paths_with_status(&["a/b", "c"]),
+            &Options::default(),
+        );
+        tree.remove("c");
+        assert_eq!(
+            render(
+                &tree,
+                &Options {
+                    flat: true,
+                    ..Default::default()
+                }
+            ),
+            "a/b\n"
+        );
+    }
+
+    #[test]
+    fn test_generate_tree_only() {
+        let paths = [
+            PathEntry::new("src/main.rs", "M"),
+            PathEntry::new("src/new.rs", "A"),
+            PathEntry::new("src/both.rs", "AM"),
+            PathEntry::new("old.rs", "D"),
+            PathEntry::new("notes.txt", "??"),
+            PathEntry::new("docs/conflict.md", "UU"),
+        ];
+        assert_eq!(
+            generate_tree_from_paths(
+                &paths,
+                &Options {
+                    only: vec!["M".to_string(), "A".to_string()],
+                    ..Default::default()
+                }
+            ),
+            r#"└── src
+    ├── both.rs
+    ├── main.rs
+    └── new.rs
+"#
+        );
+    }
+
+    #[test]
+    fn test_max_total_lines_leaves_json_alone() {
+        let paths: Vec<PathEntry> = (0..5)
+            .map(|i| PathEntry::new(&format!("src/{}.rs", i), "M"))
+            .collect();
+        let json = Options {
+            json: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            generate_tree_from_paths(
+                &paths,
+                &Options {
+                    max_total_lines: Some(1),
+                    ..json.clone()
+                }
+            ),
+            generate_tree_from_paths(&paths, &json)
+        );
+    }
+
+    #[test]
+    fn test_generate_tree_max_total_lines() {
+        let paths = create_paths_with_status(&["a/b/c.rs", "a/d.rs", "e.rs"]);
+        let render = |max_total_lines| {
+            generate_tree_from_paths(
+                &paths,
+                &Options {
+                    max_total_lines: Some(max_total_lines),
+                    ..Default::default()
+                },
+            )
+        };
+        assert_eq!(
+            render(3),
+            r#"├── a
+│   ├── b
+│   │   └── c.rs
+… (truncated, use --max-total-lines 0 for all)
+"#
+        );
+        assert_eq!(render(5), render(0));
+        assert_eq!(render(0).lines().count(), 5);
+    }
+
+    #[test]
+    fn test_generate_tree_legend_position() {
+        let paths = [
+            PathEntry::new("src/main.rs", "M"),
+            PathEntry::new("notes.txt", "??"),
+            PathEntry::new("new.rs", "A"),
+        ];
+        let render = |legend| {
+            generate_tree_from_paths(
+                &paths,
+                &Options {
+                    legend: Some(legend),
+                    ..Default::default()
+                },
+            )
+        };
+        assert_eq!(
+            render(LegendPosition::Top),
+            r#"A added, M modified, ?? untracked
+├── new.rs
+├── notes.txt
+└── src
+    └── main.rs
+"#
+        );
+        assert_eq!(
+            render(LegendPosition::Bottom),
+            r#"├── new.rs
+├── notes.txt
+└── src
+    └── main.rs
+A added, M modified, ?? untracked
+"#
+        );
+    }
+
+    #[test]
+    fn test_generate_tree_with_status_map() {
+        let paths = create_paths_with_status(&["src/main.rs", "src/lib.rs", "README.md"]);
+        let options = Options {
+            color: true,
+            status_map: HashMap::from([
+                ("src/main.rs".to_string(), "M".to_string()),
+                ("README.md".to_string(), "A".to_string()),
+                ("unlisted.rs".to_string(), "D".to_string()),
+            ]),
+            ..Default::default()
+        };
+        colored::control::set_override(true);
+        assert_eq!(
+            generate_tree_from_paths(&paths, &options),
+            "\u{1b}[90m\u{1b}[0m\u{1b}[90m├── \u{1b}[0m\u{1b}[32mREADME.md\u{1b}[0m\n\
+             \u{1b}[90m\u{1b}[0m\u{1b}[90m└── \u{1b}[0m\u{1b}[34msrc\u{1b}[0m\n\
+             \u{1b}[90m    \u{1b}[0m\u{1b}[90m├── \u{1b}[0mlib.rs\n\
+             \u{1b}[90m    \u{1b}[0m\u{1b}[90m└── \u{1b}[0m\u{1b}[33mmain.rs\u{1b}[0m\n"
+        );
+    }
+
+    #[test]
+    fn test_generate_tree_segment_colors() {
+        colored::control::set_override(true);
+        assert_eq!(
+            generate_tree_from_paths(
+                &create_paths_with_status(&["after/lsp/ts_ls.lua"]),
+                &Options {
+                    color: true,
+                    compact: true,
+                    segment_colors: true,
+                    ..Default::default()
+                }
+            ),
+            "\u{1b}[90m\u{1b}[0m\u{1b}[90m└── \u{1b}[0m\u{1b}[34mafter\u{1b}[0m\u{1b}[90m/\u{1b}[0m\u{1b}[36mlsp\u{1b}[0m\n\
+             \u{1b}[90m    \u{1b}[0m\u{1b}[90m└── \u{1b}[0mts_ls.lua\n"
+        );
+    }
+
+    #[test]
+    fn test_generate_tree_hide_hidden() {
+        let paths = create_paths_with_status(&[
+            ".gitignore",
+            ".vscode/settings.json",
+            "README.md",
+            "config/.env",
+            "src/.cache/index",
+            "src/main.rs",
+        ]);
+        assert_eq!(
+            generate_tree_from_paths(
+                &paths,
+                &Options {
+                    hide_hidden: true,
+                    ..Default::default()
+                }
+            ),
+            r#"├── README.md
+└── src
+    └── main.rs
+"#
+        );
+    }
+
+    #[test]
+    fn test_count_extensions() {
+        let paths = create_paths_with_status(&[
+            "nvim/after/lsp/tailwindcss.lua",
+            "nvim/after/lsp/ts_ls.lua",
+            "nvim/after/lsp/denols.lua",
+            "nvim/lazy-lock.json",
+            "nvim/lua/setup-lazynvim.lua",
+            "nvim/lua/install-lazynvim.lua",
+            "nvim/lua/options.lua",
+            "nvim/lua/plugins/ui.lua",
+            "nvim/lua/plugins/completion.lua",
+            "nvim/lua/plugins/treesitter.lua",
+            "nvim/lua/plugins/fuzzy-finder.lua",
+            "nvim/lua/plugins/colorscheme.lua",
+            "nvim/lua/plugins/manipulation.lua",
+            "nvim/lua/plugins/lsp.lua",
+            "nvim/lua/plugins/formatter.lua",
+            "nvim/lua/plugins/others.lua",
+            "nvim/lua/plugins/alpha-nvim.lua",
+            "nvim/lua/plugins/filer.lua",
+            "nvim/lua/use-extui.lua",
+            "nvim/lua/disable-providers.lua",
+            "nvim/lua/keymappings.lua",
+            "nvim/lua/easy-setup-autocmd/init.lua",
+            "nvim/lua/improve-default-scheme/init.lua",
+            "nvim/init.lua",
+            "nvim/ftplugin/qf.lua",
+            "nvim/Makefile",
+        ]);
+        let tree = build_tree(&paths, &Options::default());
+        assert_eq!(
+            count_extensions(&tree),
+            vec![
+                (".lua".to_string(), 24),
+                ("(none)".to_string(), 1),
+                (".json".to_string(), 1)
+            ]
+        );
+        assert_eq!(
+            render(
+                &tree,
+                &Options {
+                    json: true,
+                    ext_summary: true,
+                    ..Default::default()
+                }
+            ),
+            "{\".lua\":24,\"(none)\":1,\".json\":1}\n"
+        );
+    }
+
+    #[test]
+    fn test_generate_tree_ext_summary() {
+        assert_eq!(
+            generate_tree_from_paths(
+                &create_paths_with_status(&["src/main.rs", "src/lib.rs", "README.md"]),
+                &Options {
+                    ext_summary: true,
+                    ..Default::default()
+                }
+            ),
+            r#"├── README.md
+└── src
+    ├── lib.rs
+    └── main.rs
+.rs: 2, .md: 1
+"#
+        );
+    }
+
+    #[test]
+    fn test_generate_tree_prune_empty_dirs() {
+        let paths = [
+            PathEntry::new("config/.env", "M"),
+            PathEntry::new("build/", "??"),
+            PathEntry::new("src/main.rs", "M"),
+        ];
+        let hide_hidden = Options {
+            hide_hidden: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            generate_tree_from_paths(&paths, &hide_hidden),
+            r#"├── build (untracked dir)
+└── src
+    └── main.rs
+"#
+        );
+        assert_eq!(
+            generate_tree_from_paths(
+                &paths,
+                &Options {
+                    keep_empty_dirs: true,
+                    ..hide_hidden
+                }
+            ),
+            r#"├── build (untracked dir)
+├── config
+└── src
+    └── main.rs
+"#
+        );
+    }
+
+    #[test]
+    fn test_generate_tree_prune_empty_dirs_after_min_depth() {
+        let paths = [
+            PathEntry::new("pkg/cache/.tmp", "??"),
+            PathEntry::new("pkg/dist/", ""),
+            PathEntry::new("pkg/src/lib.rs", "M"),
+        ];
+        let options = Options {
+            hide_hidden: true,
+            min_depth: Some(2),
+            ..Default::default()
+        };
+        assert_eq!(
+            generate_tree_from_paths(&paths, &options),
+            "├── pkg/dist\n└── pkg/src\n    └── lib.rs\n"
+        );
+        assert_eq!(
+            generate_tree_from_paths(
+                &paths,
+                &Options {
+                    keep_empty_dirs: true,
+                    ..options
+                }
+            ),
+            "├── pkg/cache\n├── pkg/dist\n└── pkg/src\n    └── lib.rs\n"
+        );
+    }
+
+    #[test]
+    fn test_generate_tree_keeps_listed_empty_dirs() {
+        let paths = [PathEntry::new("build/", ""), PathEntry::new("src/a.rs", "")];
+        assert_eq!(
+            generate_tree_from_paths(&paths, &Options::default()),
+            "├── build\n└── src\n    └── a.rs\n"
+        );
+        let tree = build_tree(&paths, &Options::default());
+        assert_eq!(tree_from_flat(&tree_to_flat(&tree)), tree);
+    }
+
+    #[test]
+    fn test_generate_tree_status_bg() {
+        colored::control::set_override(true);
+        assert_eq!(
+            generate_tree_from_paths(
+                &[PathEntry::new("new.rs", "A"), PathEntry::new("lib.rs", "")],
+                &Options {
+                    color: true,
+                    status_bg: true,
+                    ..Default::default()
+                }
+            ),
+            "\u{1b}[90m\u{1b}[0m\u{1b}[90m├── \u{1b}[0mlib.rs\n\
+             \u{1b}[90m\u{1b}[0m\u{1b}[90m└── \u{1b}[0m\u{1b}[42;30mnew.rs\u{1b}[0m\n"
+        );
+    }
+
+    #[test]
+    fn test_generate_tree_tree_chars() {
+        let paths = create_paths_with_status(&["src/lib.rs", "src/main.rs", "README.md"]);
+        let render = |tree_chars| {
+            generate_tree_from_paths(
+                &paths,
+                &Options {
+                    tree_chars,
+                    ..Default::default()
+                },
+            )
+        };
+        assert_eq!(
+            render(TreeChars::Utf8),
+            r#"├── README.md
+└── src
+    ├── lib.rs
+    └── main.rs
+"#
+        );
+        assert_eq!(
+            render(TreeChars::Ascii),
+            r#"|-- README.md
+`-- src
+    |-- lib.rs
+    `-- main.rs
+"#
+        );
+        assert_eq!(
+            render(TreeChars::Rounded),
+            r#"├── README.md
+╰── src
+    ├── lib.rs
+    ╰── main.rs
+"#
+        );
+        assert_eq!(
+            render(TreeChars::Double),
+            r#"╠══ README.md
+╚══ src
+    ╠══ lib.rs
+    ╚══ main.rs
+"#
+        );
+    }
+
+    #[test]
+    fn test_generate_tree_tree_chars_guides() {
+        assert_eq!(
+            generate_tree_from_paths(
+                &create_paths_with_status(&["src/main.rs", "tests/cli.rs"]),
+                &Options {
+                    tree_chars: TreeChars::Double,
+                    ..Default::default()
+                }
+            ),
+            r#"╠══ src
+║   ╚══ main.rs
+╚══ tests
+    ╚══ cli.rs
+"#
+        );
+    }
+
+    #[test]
+    fn test_generate_tree_dashboard() {
+        let paths = [
+            PathEntry::new("src/main.rs", "M"),
+            PathEntry::new("src/parser.rs", "M"),
+            PathEntry::new("src/json.rs", "A"),
+            PathEntry::new("tests/cli.rs", "A"),
+            PathEntry::new("tests/fixtures/status.txt", "A"),
+            PathEntry::new("tests/fixtures/numstat.txt", "A"),
+            PathEntry::new("docs/usage.md", "D"),
+            PathEntry::new("README.md", "M"),
+        ];
+        let options = Options {
+            dashboard: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            generate_tree_from_paths(&paths, &options),
+            r#"src/      M2 A1
+tests/    A3
+./        M1
+docs/     D1
+"#
+        );
+        assert_eq!(
+            generate_tree_from_paths(
+                &paths,
+                &Options {
+                    json: true,
+                    ..options
+                }
+            ),
+            r#"[{"name":"src","total":3,"statuses":{"M":2,"A":1}},{"name":"tests","total":3,"statuses":{"A":3}},{"name":".","total":1,"statuses":{"M":1}},{"name":"docs","total":1,"statuses":{"D":1}}]
+"#
+        );
+    }
+
+    #[test]
+    fn test_generate_tree_compact_keeps_directory_status() {
+        let paths = [
+            PathEntry::new("vendor/", "??"),
+            PathEntry::new("vendor/lib/util/x.rs", ""),
+        ];
+        assert_eq!(
+            generate_tree_from_paths(
+                &paths,
+                &Options {
+                    compact: true,
+                    ..Default::default()
+                }
+            ),
+            r#"└── vendor (untracked dir)
+    └── lib/util
+        └── x.rs
+"#
+        );
+    }
+
+    #[test]
+    fn test_generate_tree_input_order() {
+        let paths = create_paths_with_status(&["zeta.rs", "src/main.rs", "alpha.rs", "src/lib.rs"]);
+        assert_eq!(
+            generate_tree_from_paths(
+                &paths,
+                &Options {
+                    sort: SortOrder::Input,
+                    ..Default::default()
+                }
+            ),
+            r#"├── zeta.rs
+├── src
+│   ├── main.rs
+│   └── lib.rs
+└── alpha.rs
+"#
+        );
+        assert_eq!(
+            generate_tree_from_paths(&paths, &Options::default()),
+            r#"├── alpha.rs
+├── src
+│   ├── lib.rs
+│   └── main.rs
+└── zeta.rs
+"#
+        );
+    }
+
+    #[test]
+    fn test_generate_tree_status_glyphs() {
+        let paths = [
+            PathEntry::new("README.md", "M"),
+            PathEntry::new("src/lib.rs", ""),
+            PathEntry::new("src/main.rs", "A"),
+        ];
+        let options = Options {
+            status_glyphs: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            generate_tree_from_paths(&paths, &options),
+            r#"├── ● README.md
+└── src
+    ├──   lib.rs
+    └── ● main.rs
+"#
+        );
+
+        colored::control::set_override(true);
+        assert_eq!(
+            generate_tree_from_paths(
+                &paths,
+                &Options {
+                    color: true,
+                    ..options
+                }
+            ),
+            "\u{1b}[90m\u{1b}[0m\u{1b}[90m├── \u{1b}[0m\u{1b}[33m●\u{1b}[0m README.md\n\
+             \u{1b}[90m\u{1b}[0m\u{1b}[90m└── \u{1b}[0m\u{1b}[34msrc\u{1b}[0m\n\
+             \u{1b}[90m    \u{1b}[0m\u{1b}[90m├── \u{1b}[0m  lib.rs\n\
+             \u{1b}[90m    \u{1b}[0m\u{1b}[90m└── \u{1b}[0m\u{1b}[32m●\u{1b}[0m main.rs\n"
+        );
+    }
+
+    #[test]
+    fn test_generate_tree_highlight_extensions() {
+        colored::control::set_override(true);
+        assert_eq!(
+            generate_tree_from_paths(
+                &[
+                    PathEntry::new("init.lua", "M"),
+                    PathEntry::new("lazy-lock.json", ""),
+                    PathEntry::new("lua/Options.LUA", ""),
+                ],
+                &Options {
+                    color: true,
+                    highlight_extensions: vec!["lua".to_string()],
+                    ..Default::default()
+                }
+            ),
+            "\u{1b}[90m\u{1b}[0m\u{1b}[90m├── \u{1b}[0m\u{1b}[1;4;33minit.lua\u{1b}[0m\n\
+             \u{1b}[90m\u{1b}[0m\u{1b}[90m├── \u{1b}[0mlazy-lock.json\n\
+             \u{1b}[90m\u{1b}[0m\u{1b}[90m└── \u{1b}[0m\u{1b}[34mlua\u{1b}[0m\n\
+             \u{1b}[90m    \u{1b}[0m\u{1b}[90m└── \u{1b}[0m\u{1b}[1;4mOptions.LUA\u{1b}[0m\n"
+        );
+    }
+
+    #[test]
+    fn test_generate_tree_counts() {
+        let paths = create_paths_with_status(&[
+            "src/main.rs",
+            "src/lib.rs",
+            "src/parser/mod.rs",
+            "src/parser/lexer/token.rs",
+        ]);
+        let render = |mode| {
+            generate_tree_from_paths(
+                &paths,
+                &Options {
+                    counts: Some(mode),
+                    ..Default::default()
+                },
+            )
+        };
+        assert_eq!(
+            render(CountMode::Files),
+            r#"└── src (4 files)
+    ├── lib.rs
+    ├── main.rs
+    └── parser (2 files)
+        ├── lexer (1 file)
+        │   └── token.rs
+        └── mod.rs
+"#
+        );
+        assert_eq!(
+            render(CountMode::Dirs),
+            r#"└── src (2 dirs)
+    ├── lib.rs
+    ├── main.rs
+    └── parser (1 dir)
+        ├── lexer (0 dirs)
+        │   └── token.rs
+        └── mod.rs
+"#
+        );
+        assert_eq!(
+            render(CountMode::All),
+            r#"└── src (6 entries)
+    ├── lib.rs
+    ├── main.rs
+    └── parser (3 entries)
+        ├── lexer (1 entry)
+        │   └── token.rs
+        └── mod.rs
+"#
+        );
+    }
+
+    #[test]
+    fn test_generate_tree_normalize_unicode() {
+        let paths = [
+            PathEntry::new("docs/café.md", "M"),
+            PathEntry::new("docs/cafe\u{301}.md", "M"),
+        ];
+        assert_eq!(
+            generate_tree_from_paths(&paths, &Options::default()),
+            "└── docs\n    ├── cafe\u{301}.md\n    └── café.md\n"
+        );
+        assert_eq!(
+            generate_tree_from_paths(
+                &paths,
+                &Options {
+                    normalize_unicode: Some(NormalizationForm::Nfc),
+                    ..Default::default()
                 }
             ),
-            r#"└── nvim
-    ├── after
-    │   └── lsp
-    │       ├── denols.lua
-    │       ├── tailwindcss.lua
-    │       └── ts_ls.lua
-    ├── ftplugin
-    │   └── qf.lua
-    ├── init.lua
-    ├── lazy-lock.json
-    └── lua
-        ├── disable-providers.lua
-        ├── easy-setup-autocmd
-        │   └── init.lua
-        ├── improve-default-scheme
-        │   └── init.lua
-        ├── install-lazynvim.lua
-        ├── keymappings.lua
-        ├── options.lua
-        ├── plugins
-        │   ├── alpha-nvim.lua
-        │   ├── colorscheme.lua
-        │   ├── completion.lua
-        │   ├── filer.lua
-        │   ├── formatter.lua
-        │   ├── fuzzy-finder.lua
-        │   ├── lsp.lua
-        │   ├── manipulation.lua
-        │   ├── others.lua
-        │   ├── treesitter.lua
-        │   └── ui.lua
-        ├── setup-lazynvim.lua
-        └── use-extui.lua
-"#
+            "└── docs\n    └── café.md\n"
         );
     }
 
     #[test]
-    fn test_generate_tree_compact() {
+    fn test_generate_tree_folded() {
+        let paths = [
+            PathEntry {
+                numstat: Some(Numstat {
+                    added: Some(12),
+                    deleted: Some(3),
+                }),
+                ..PathEntry::new("src/main.rs", "")
+            },
+            PathEntry {
+                numstat: Some(Numstat {
+                    added: None,
+                    deleted: None,
+                }),
+                ..PathEntry::new("assets/logo.png", "")
+            },
+            PathEntry::new("src/parser/mod.rs", ""),
+            PathEntry::new("build/", "??"),
+        ];
         assert_eq!(
             generate_tree_from_paths(
-                &create_paths_with_status(&[
-                    "dotfiles/nvim/after/lsp/tailwindcss.lua",
-                    "dotfiles/nvim/after/lsp/ts_ls.lua",
-                    "dotfiles/nvim/after/lsp/denols.lua",
-                    "dotfiles/nvim/lazy-lock.json",
-                    "dotfiles/nvim/lua/setup-lazynvim.lua",
-                    "dotfiles/nvim/lua/install-lazynvim.lua",
-                    "dotfiles/nvim/lua/options.lua",
-                    "dotfiles/nvim/lua/plugins/ui.lua",
-                    "dotfiles/nvim/lua/plugins/completion.lua",
-                    "dotfiles/nvim/lua/plugins/treesitter.lua",
-                    "dotfiles/nvim/lua/plugins/fuzzy-finder.lua",
-                    "dotfiles/nvim/lua/plugins/colorscheme.lua",
-                    "dotfiles/nvim/lua/plugins/manipulation.lua",
-                    "dotfiles/nvim/lua/plugins/lsp.lua",
-                    "dotfiles/nvim/lua/plugins/formatter.lua",
-                    "dotfiles/nvim/lua/plugins/others.lua",
-                    "dotfiles/nvim/lua/plugins/alpha-nvim.lua",
-                    "dotfiles/nvim/lua/plugins/filer.lua",
-                    "dotfiles/nvim/lua/use-extui.lua",
-                    "dotfiles/nvim/lua/disable-providers.lua",
-                    "dotfiles/nvim/lua/keymappings.lua",
-                    "dotfiles/nvim/lua/easy-setup-autocmd/init.lua",
-                    "dotfiles/nvim/lua/improve-default-scheme/init.lua",
-                    "dotfiles/nvim/init.lua",
-                    "dotfiles/nvim/ftplugin/qf.lua"
-                ]),
+                &paths,
                 &Options {
-                    compact: true,
-                    color: false
+                    folded: true,
+                    ..Default::default()
                 }
             ),
-            r#"└── dotfiles/nvim
-    ├── after/lsp
-    │   ├── denols.lua
-    │   ├── tailwindcss.lua
-    │   └── ts_ls.lua
-    ├── ftplugin
-    │   └── qf.lua
-    ├── init.lua
-    ├── lazy-lock.json
-    └── lua
-        ├── disable-providers.lua
-        ├── easy-setup-autocmd
-        │   └── init.lua
-        ├── improve-default-scheme
-        │   └── init.lua
-        ├── install-lazynvim.lua
-        ├── keymappings.lua
-        ├── options.lua
-        ├── plugins
-        │   ├── alpha-nvim.lua
-        │   ├── colorscheme.lua
-        │   ├── completion.lua
-        │   ├── filer.lua
-        │   ├── formatter.lua
-        │   ├── fuzzy-finder.lua
-        │   ├── lsp.lua
-        │   ├── manipulation.lua
-        │   ├── others.lua
-        │   ├── treesitter.lua
-        │   └── ui.lua
-        ├── setup-lazynvim.lua
-        └── use-extui.lua
+            r#"assets;logo.png 1
+build 1
+src;main.rs 15
+src;parser;mod.rs 1
 "#
         );
     }
 
     #[test]
-    fn test_format_tree_as_lines() {
-        let mut tree = Tree::new();
-        add_path_to_tree(&mut tree, Path::new("a/b"), Some("M".to_string()));
-        add_path_to_tree(&mut tree, Path::new("a/c"), Some("A".to_string()));
+    fn test_collapse_pattern() {
+        let paths = create_paths_with_status(&[
+            "web/node_modules/react/index.js",
+            "web/node_modules/react/package.json",
+            "web/node_modules/.bin/tsc",
+            "web/src/app.ts",
+            "target/debug/app",
+            "README.md",
+        ]);
+        let options = Options {
+            collapse_pattern: Some("node_modules|^target$".parse().unwrap()),
+            ..Default::default()
+        };
+        assert_eq!(
+            generate_tree_from_paths(&paths, &options),
+            r#"├── README.md
+├── target (… 2 entries)
+└── web
+    ├── node_modules (… 5 entries)
+    └── src
+        └── app.ts
+"#
+        );
+    }
 
-        let lines = format_tree_as_entries(&tree, "", false);
+    #[test]
+    fn test_root_status() {
+        let paths = vec![
+            PathEntry::new("src/main.rs", "M"),
+            PathEntry::new("src/lib.rs", "M"),
+            PathEntry::new("src/new.rs", "A"),
+            PathEntry::new("old.rs", "D"),
+            PathEntry::new("notes.txt", "??"),
+            PathEntry::new("tmp/a", "??"),
+            PathEntry::new("tmp/b", "??"),
+            PathEntry::new("README.md", ""),
+        ];
+        let options = Options {
+            root_status: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            generate_tree_from_paths(&paths, &options),
+            r#"● 1D 1A 2M 3??
+├── README.md
+├── notes.txt
+├── old.rs
+├── src
+│   ├── lib.rs
+│   ├── main.rs
+│   └── new.rs
+└── tmp
+    ├── a
+    └── b
+"#
+        );
+    }
 
+    #[test]
+    fn test_brackets() {
+        let paths = create_paths_with_status(&["src/lib.rs", "src/main.rs", "tests/cli.rs"]);
+        let options = Options {
+            brackets: true,
+            ..Default::default()
+        };
         assert_eq!(
-            lines,
-            vec![
-                LineEntry::Indent("".to_string()),
-                LineEntry::Connector("└── ".to_string()),
-                LineEntry::Directory("a".to_string()),
-                LineEntry::Indent("    ".to_string()),
-                LineEntry::Connector("├── ".to_string()),
-                LineEntry::File("b".to_string(), Some("M".to_string())),
-                LineEntry::Indent("    ".to_string()),
-                LineEntry::Connector("└── ".to_string()),
-                LineEntry::File("c".to_string(), Some("A".to_string()))
-            ]
+            generate_tree_from_paths(&paths, &options),
+            r#"├── src
+│   ┌─
+│   ├── lib.rs
+│   └── main.rs
+│   └─
+└── tests
+    ┌─
+    └── cli.rs
+    └─
+"#
         );
     }
 
     #[test]
-    fn test_generate_tree_with_color() {
+    fn test_max_siblings() {
+        let mut paths: Vec<String> = (0..10).map(|i| format!("logs/{}.log", i)).collect();
+        paths.push("README.md".to_string());
+        let paths: Vec<&str> = paths.iter().map(String::as_str).collect();
+        let options = Options {
+            max_siblings: Some(3),
+            ..Default::default()
+        };
+        assert_eq!(
+            generate_tree_from_paths(&create_paths_with_status(&paths), &options),
+            r#"├── README.md
+└── logs
+    ├── 0.log
+    ├── 1.log
+    ├── 2.log
+    └── +7 more files
+"#
+        );
+    }
+
+    #[test]
+    fn test_aliases() {
+        let paths = create_paths_with_status(&["src/main.rs", "lib/src/util.rs", "build/out.txt"]);
+        let options = Options {
+            aliases: vec![
+                ("src".to_string(), "source".to_string()),
+                ("build".to_string(), ".".to_string()),
+            ],
+            ..Default::default()
+        };
+        assert_eq!(
+            generate_tree_from_paths(&paths, &options),
+            r#"├── lib
+│   └── src
+│       └── util.rs
+├── out.txt
+└── source
+    └── main.rs
+"#
+        );
+        let options = Options {
+            alias_all: true,
+            ..options
+        };
+        assert_eq!(
+            generate_tree_from_paths(&paths, &options),
+            r#"├── lib
+│   └── source
+│       └── util.rs
+├── out.txt
+└── source
+    └── main.rs
+"#
+        );
+    }
+
+    #[test]
+    fn test_sort_key() {
+        let paths = vec![
+            PathEntry::new("b.rs", "M"),
+            PathEntry::new("a.toml", ""),
+            PathEntry::new("c.md", "??"),
+            PathEntry::new("d.rs", ""),
+            PathEntry::new("Makefile", "A"),
+            PathEntry::new("docs/intro.md", "D"),
+            PathEntry::new("docs.txt", ""),
+        ];
+        let render = |sort_key: SortKey, reverse: bool| {
+            generate_tree_from_paths(
+                &paths,
+                &Options {
+                    sort_key,
+                    reverse,
+                    flat: true,
+                    ..Default::default()
+                },
+            )
+        };
+        assert_eq!(
+            render(SortKey::Ext, false),
+            r#"Makefile
+docs/intro.md
+c.md
+b.rs
+d.rs
+a.toml
+docs.txt
+"#
+        );
+        assert_eq!(
+            render(SortKey::Status, false),
+            r#"docs/intro.md
+Makefile
+b.rs
+c.md
+a.toml
+d.rs
+docs.txt
+"#
+        );
+        assert_eq!(
+            render(SortKey::Status, true),
+            r#"docs.txt
+d.rs
+a.toml
+c.md
+b.rs
+Makefile
+docs/intro.md
+"#
+        );
+        assert_eq!(
+            render(SortKey::Path, false),
+            r#"Makefile
+a.toml
+b.rs
+c.md
+d.rs
+docs.txt
+docs/intro.md
+"#
+        );
+    }
+
+    #[test]
+    fn test_show_depth() {
+        let paths = create_paths_with_status(&[
+            "nvim/init.lua",
+            "nvim/lua/options.lua",
+            "nvim/after/lsp/denols.lua",
+            "README.md",
+        ]);
+        let options = Options {
+            show_depth: true,
+            compact: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            generate_tree_from_paths(&paths, &options),
+            r#"├── README.md [d1]
+└── nvim [d1]
+    ├── after/lsp [d3]
+    │   └── denols.lua [d4]
+    ├── init.lua [d2]
+    └── lua [d2]
+        └── options.lua [d3]
+"#
+        );
+    }
+
+    #[test]
+    fn test_compact_separator() {
+        let paths = create_paths_with_status(&["nvim/after/lsp/denols.lua", "nvim/init.lua"]);
+        let options = Options {
+            compact: true,
+            compact_separator: Some(" › ".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            generate_tree_from_paths(&paths, &options),
+            r#"└── nvim
+    ├── after › lsp
+    │   └── denols.lua
+    └── init.lua
+"#
+        );
+    }
+
+    #[test]
+    fn test_status_codes() {
         colored::control::set_override(true);
         let paths = vec![
-            ("a/b".to_string(), "M".to_string()),
-            ("a/c".to_string(), "A".to_string()),
+            PathEntry::new("changed.txt", "changed"),
+            PathEntry::new("new.txt", "new"),
         ];
-        let options = &Options {
-            compact: false,
+        let options = Options {
             color: true,
+            status_codes: HashMap::from([
+                ("changed".to_string(), "M".to_string()),
+                ("new".to_string(), "A".to_string()),
+            ]),
+            ..Default::default()
+        };
+        assert_eq!(generate_tree_from_paths(&paths, &options), "\u{1b}[90m\u{1b}[0m\u{1b}[90m├── \u{1b}[0m\u{1b}[33mchanged.txt\u{1b}[0m\n\u{1b}[90m\u{1b}[0m\u{1b}[90m└── \u{1b}[0m\u{1b}[32mnew.txt\u{1b}[0m\n");
+    }
+
+    #[test]
+    fn test_sibling_lines_share_their_prefix() {
+        let paths = create_paths_with_status(&["a/b/c.rs", "a/b/d.rs", "a/b/e.rs"]);
+        let tree = build_tree(&paths, &Options::default());
+        let indents: Vec<Rc<str>> = format_tree_as_entries(&tree, "", "", &Options::default())
+            .into_iter()
+            .filter_map(|entry| match entry {
+                LineEntry::Indent(prefix) => Some(prefix),
+                _ => None,
+            })
+            .collect();
+        let [_, _, c, d, e] = &indents[..] else {
+            panic!("expected 5 lines, got {:?}", indents);
+        };
+        assert_eq!(&**c, "        ");
+        assert!(Rc::ptr_eq(c, d) && Rc::ptr_eq(d, e));
+    }
+
+    #[test]
+    fn test_shared_prefixes_match_unshared_output() {
+        let paths = nvim_paths();
+        let option_sets = [
+            Options::default(),
+            Options {
+                compact: true,
+                ..Default::default()
+            },
+            Options {
+                bare_root: true,
+                status_display: Some(StatusDisplay::Raw),
+                ..Default::default()
+            },
+            Options {
+                wrap: Some(12),
+                brackets: true,
+                ..Default::default()
+            },
+            Options {
+                merge_files: true,
+                tree_chars: TreeChars::Ascii,
+                ..Default::default()
+            },
+        ];
+        for options in option_sets {
+            let unshared = Options {
+                unshared_prefixes: true,
+                ..options.clone()
+            };
+            assert_eq!(
+                generate_tree_from_paths(&paths, &options),
+                generate_tree_from_paths(&paths, &unshared)
+            );
+        }
+    }
+
+    #[test]
+    fn test_icon_legend() {
+        let paths = create_paths_with_status(&["src/main.rs", "init.lua", "notes.xyz", "Makefile"]);
+        let options = Options {
+            icons: true,
+            icon_legend: Some(LegendPosition::Bottom),
+            ..Default::default()
         };
         assert_eq!(
-            generate_tree_from_paths(&paths, options),
-            "\u{1b}[90m\u{1b}[0m\u{1b}[90m└── \u{1b}[0m\u{1b}[34ma\u{1b}[0m\n\u{1b}[90m    \u{1b}[0m\u{1b}[90m├── \u{1b}[0m\u{1b}[33mb\u{1b}[0m\n\u{1b}[90m    \u{1b}[0m\u{1b}[90m└── \u{1b}[0m\u{1b}[32mc\u{1b}[0m\n"
+            generate_tree_from_paths(&paths, &options),
+            "├── \u{f15b} Makefile\n\
+             ├── \u{e620} init.lua\n\
+             ├── \u{f15b} notes.xyz\n\
+             └── src\n\
+             \x20   └── \u{e7a8} main.rs\n\
+             \u{e620} .lua  \u{e7a8} .rs\n"
         );
     }
 
     #[test]
-    fn test_generate_tree_from_porcelain_output() {
+    fn test_path_case() {
+        let paths = create_paths_with_status(&["src/Main.rs", "src/lib.rs", "README.md"]);
+        let options = Options {
+            path_case: PathCase::Upper,
+            ..Default::default()
+        };
+        assert_eq!(
+            generate_tree_from_paths(&paths, &options),
+            r#"├── README.MD
+└── SRC
+    ├── MAIN.RS
+    └── LIB.RS
+"#
+        );
+    }
+
+    #[test]
+    fn test_bars() {
+        let numstat = |added, deleted| Numstat { added, deleted };
+        let paths = vec![
+            PathEntry {
+                numstat: Some(numstat(Some(30), Some(10))),
+                ..PathEntry::new("src/big.rs", "")
+            },
+            PathEntry {
+                numstat: Some(numstat(Some(2), Some(0))),
+                ..PathEntry::new("src/small.rs", "")
+            },
+            PathEntry {
+                numstat: Some(numstat(Some(5), Some(5))),
+                ..PathEntry::new("README.md", "")
+            },
+            PathEntry {
+                numstat: Some(numstat(None, None)),
+                ..PathEntry::new("logo.png", "")
+            },
+        ];
+        let options = Options {
+            bars: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            generate_tree_from_paths(&paths, &options),
+            r#"├── README.md 10 +++---
+├── logo.png Bin
+└── src (+32 -10)
+    ├── big.rs 40 +++++++++++++++-----
+    └── small.rs 2 +
+"#
+        );
+    }
+
+    #[test]
+    fn test_untracked_last() {
+        let paths = vec![
+            PathEntry::new("src/a.rs", "??"),
+            PathEntry::new("src/b.rs", "M"),
+            PathEntry::new("src/c.log", "!!"),
+            PathEntry::new("src/d.rs", "A"),
+            PathEntry::new("src/e.rs", ""),
+        ];
+        let options = Options {
+            untracked_last: true,
+            sort_key: SortKey::Ext,
+            ..Default::default()
+        };
+        assert_eq!(
+            generate_tree_from_paths(&paths, &options),
+            r#"└── src
+    ├── b.rs
+    ├── d.rs
+    ├── e.rs
+    ├── c.log
+    └── a.rs
+"#
+        );
+    }
+
+    #[test]
+    fn test_merge_status_columns() {
+        let paths = vec![
+            PathEntry::new("staged.rs", "M."),
+            PathEntry::new("unstaged.rs", ".M"),
+            PathEntry::new("both.rs", "MM"),
+            PathEntry::new("added.rs", "A."),
+        ];
+        let render = |merge_status_columns| {
+            generate_tree_from_paths(
+                &paths,
+                &Options {
+                    only: vec!["M".to_string()],
+                    merge_status_columns,
+                    flat: true,
+                    ..Default::default()
+                },
+            )
+        };
+        assert_eq!(render(ColumnMerge::Or), "both.rs\nstaged.rs\nunstaged.rs\n");
+        assert_eq!(render(ColumnMerge::And), "both.rs\n");
+    }
+
+    #[test]
+    fn test_color_scope() {
         colored::control::set_override(true);
+        let paths = vec![PathEntry::new("src/main.rs", "M")];
+        let render = |color_scope| {
+            generate_tree_from_paths(
+                &paths,
+                &Options {
+                    color: true,
+                    status_glyphs: true,
+                    color_scope,
+                    ..Default::default()
+                },
+            )
+        };
+        let all = render(ColorScope::All);
+        assert!(all.contains("\u{1b}[90m└── \u{1b}[0m"));
+        assert!(all.contains("\u{1b}[34msrc\u{1b}[0m"));
+
+        let names = render(ColorScope::Names);
+        assert!(!names.contains("\u{1b}[90m└── "));
+        assert!(names.contains("\u{1b}[34msrc\u{1b}[0m"));
+        assert!(names.contains("\u{1b}[33m●\u{1b}[0m"));
+
+        let status_only = render(ColorScope::StatusOnly);
+        assert!(!status_only.contains("\u{1b}[90m└── "));
+        assert!(!status_only.contains("\u{1b}[34msrc"));
+        assert!(status_only.contains(" main.rs\n"));
+        assert!(status_only.contains("\u{1b}[33m●\u{1b}[0m"));
+    }
+
+    #[test]
+    fn test_tree_only_changed_leaves() {
         let paths = vec![
-            ("src/main.rs".to_string(), "M".to_string()),
-            ("src/tree_generator.rs".to_string(), "M".to_string()),
-            ("new_file.txt".to_string(), "A".to_string()),
-            ("deleted_file.txt".to_string(), "D".to_string()),
-            ("renamed_file.txt".to_string(), "R".to_string()),
-            ("copied_file.txt".to_string(), "C".to_string()),
-            ("unmerged_file.txt".to_string(), "U".to_string()),
-            ("untracked_file.txt".to_string(), "??".to_string()),
+            PathEntry::new("src/utils/helpers.rs", "M"),
+            PathEntry::new("src/main.rs", ""),
+            PathEntry::new("README.md", "A"),
+            PathEntry::new("src/lib.rs", "MM"),
+            PathEntry::new("docs/guide.md", ""),
+            PathEntry::new("build/", "??"),
         ];
-        let options = &Options {
-            compact: false,
-            color: true,
+        let options = Options {
+            tree_only_changed_leaves: true,
+            ..Default::default()
         };
-        let expected = "\u{1b}[90m\u{1b}[0m\u{1b}[90m├── \u{1b}[0m\u{1b}[35mcopied_file.txt\u{1b}[0m\n\u{1b}[90m\u{1b}[0m\u{1b}[90m├── \u{1b}[0m\u{1b}[31mdeleted_file.txt\u{1b}[0m\n\u{1b}[90m\u{1b}[0m\u{1b}[90m├── \u{1b}[0m\u{1b}[32mnew_file.txt\u{1b}[0m\n\u{1b}[90m\u{1b}[0m\u{1b}[90m├── \u{1b}[0m\u{1b}[36mrenamed_file.txt\u{1b}[0m\n\u{1b}[90m\u{1b}[0m\u{1b}[90m├── \u{1b}[0m\u{1b}[34msrc\u{1b}[0m\n\u{1b}[90m│   \u{1b}[0m\u{1b}[90m├── \u{1b}[0m\u{1b}[33mmain.rs\u{1b}[0m\n\u{1b}[90m│   \u{1b}[0m\u{1b}[90m└── \u{1b}[0m\u{1b}[33mtree_generator.rs\u{1b}[0m\n\u{1b}[90m\u{1b}[0m\u{1b}[90m├── \u{1b}[0m\u{1b}[1;31munmerged_file.txt\u{1b}[0m\n\u{1b}[90m\u{1b}[0m\u{1b}[90m└── \u{1b}[0m\u{1b}[90muntracked_file.txt\u{1b}[0m\n";
+        assert_eq!(
+            generate_tree_from_paths(&paths, &options),
+            "├── README.md A\n├── build/ ??\n├── src/lib.rs MM\n└── src/utils/helpers.rs M\n"
+        );
 
-        assert_eq!(generate_tree_from_paths(&paths, options), expected);
+        colored::control::set_override(true);
+        let colored = generate_tree_from_paths(
+            &paths,
+            &Options {
+                color: true,
+                ..options
+            },
+        );
+        assert!(colored.contains("\u{1b}[32mREADME.md\u{1b}[0m"));
+    }
+
+    #[test]
+    fn test_columns_with_empty_tree() {
+        let options = Options {
+            columns: Some(2),
+            ..Default::default()
+        };
+        assert_eq!(generate_tree_from_paths(&[], &options), "");
+        let paths = [PathEntry::new("src/main.rs", "M")];
+        assert_eq!(
+            generate_tree_from_paths(
+                &paths,
+                &Options {
+                    only: vec!["A".to_string()],
+                    ..options
+                }
+            ),
+            ""
+        );
     }
 }
@@ -0,0 +1,213 @@
+/// A Unicode normalization form for `--normalize-unicode`.
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq)]
+pub enum NormalizationForm {
+    /// Composed, as most systems write names: `é` is one character.
+    Nfc,
+    /// Decomposed, as macOS file systems often give names: `é` is `e` and a combining accent.
+    Nfd,
+}
+
+/// Normalizes `s` to `form`, so that names that look the same compare equal.
+///
+/// Only the precomposed Latin letters up to U+024F, the Vietnamese ones of U+1E00..=U+1EFF and
+/// the kana with (semi-)voiced sound marks are known. Other characters are left as they are, and
+/// combining marks are not reordered.
+pub fn normalize(s: &str, form: NormalizationForm) -> String {
+    let mut decomposed = String::with_capacity(s.len());
+    for c in s.chars() {
+        decompose_into(c, &mut decomposed);
+    }
+    if form == NormalizationForm::Nfd {
+        return decomposed;
+    }
+    let mut composed = String::with_capacity(decomposed.len());
+    let mut pending: Option<char> = None;
+    for c in decomposed.chars() {
+        match pending.and_then(|base| compose(base, c)) {
+            Some(combined) => pending = Some(combined),
+            None => {
+                composed.extend(pending);
+                pending = Some(c);
+            }
+        }
+    }
+    composed.extend(pending);
+    composed
+}
+
+fn decompose_into(c: char, out: &mut String) {
+    match COMPOSITIONS.binary_search_by_key(&c, |&(composed, _, _)| composed) {
+        Ok(i) => {
+            let (_, base, mark) = COMPOSITIONS[i];
+            decompose_into(base, out);
+            out.push(mark);
+        }
+        Err(_) => out.push(c),
+    }
+}
+
+fn compose(base: char, mark: char) -> Option<char> {
+    COMPOSITIONS
+        .iter()
+        .find(|&&(_, b, m)| b == base && m == mark)
+        .map(|&(composed, _, _)| composed)
+}
+
+/// `(composed, base, mark)` for every character `normalize` knows, ordered by `composed`.
+#[rustfmt::skip]
+const COMPOSITIONS: &[(char, char, char)] = &[
+    ('À', 'A', '\u{300}'), ('Á', 'A', '\u{301}'), ('Â', 'A', '\u{302}'), ('Ã', 'A', '\u{303}'),
+    ('Ä', 'A', '\u{308}'), ('Å', 'A', '\u{30a}'), ('Ç', 'C', '\u{327}'), ('È', 'E', '\u{300}'),
+    ('É', 'E', '\u{301}'), ('Ê', 'E', '\u{302}'), ('Ë', 'E', '\u{308}'), ('Ì', 'I', '\u{300}'),
+    ('Í', 'I', '\u{301}'), ('Î', 'I', '\u{302}'), ('Ï', 'I', '\u{308}'), ('Ñ', 'N', '\u{303}'),
+    ('Ò', 'O', '\u{300}'), ('Ó', 'O', '\u{301}'), ('Ô', 'O', '\u{302}'), ('Õ', 'O', '\u{303}'),
+    ('Ö', 'O', '\u{308}'), ('Ù', 'U', '\u{300}'), ('Ú', 'U', '\u{301}'), ('Û', 'U', '\u{302}'),
+    ('Ü', 'U', '\u{308}'), ('Ý', 'Y', '\u{301}'), ('à', 'a', '\u{300}'), ('á', 'a', '\u{301}'),
+    ('â', 'a', '\u{302}'), ('ã', 'a', '\u{303}'), ('ä', 'a', '\u{308}'), ('å', 'a', '\u{30a}'),
+    ('ç', 'c', '\u{327}'), ('è', 'e', '\u{300}'), ('é', 'e', '\u{301}'), ('ê', 'e', '\u{302}'),
+    ('ë', 'e', '\u{308}'), ('ì', 'i', '\u{300}'), ('í', 'i', '\u{301}'), ('î', 'i', '\u{302}'),
+    ('ï', 'i', '\u{308}'), ('ñ', 'n', '\u{303}'), ('ò', 'o', '\u{300}'), ('ó', 'o', '\u{301}'),
+    ('ô', 'o', '\u{302}'), ('õ', 'o', '\u{303}'), ('ö', 'o', '\u{308}'), ('ù', 'u', '\u{300}'),
+    ('ú', 'u', '\u{301}'), ('û', 'u', '\u{302}'), ('ü', 'u', '\u{308}'), ('ý', 'y', '\u{301}'),
+    ('ÿ', 'y', '\u{308}'), ('Ā', 'A', '\u{304}'), ('ā', 'a', '\u{304}'), ('Ă', 'A', '\u{306}'),
+    ('ă', 'a', '\u{306}'), ('Ą', 'A', '\u{328}'), ('ą', 'a', '\u{328}'), ('Ć', 'C', '\u{301}'),
+    ('ć', 'c', '\u{301}'), ('Ĉ', 'C', '\u{302}'), ('ĉ', 'c', '\u{302}'), ('Ċ', 'C', '\u{307}'),
+    ('ċ', 'c', '\u{307}'), ('Č', 'C', '\u{30c}'), ('č', 'c', '\u{30c}'), ('Ď', 'D', '\u{30c}'),
+    ('ď', 'd', '\u{30c}'), ('Ē', 'E', '\u{304}'), ('ē', 'e', '\u{304}'), ('Ĕ', 'E', '\u{306}'),
+    ('ĕ', 'e', '\u{306}'), ('Ė', 'E', '\u{307}'), ('ė', 'e', '\u{307}'), ('Ę', 'E', '\u{328}'),
+    ('ę', 'e', '\u{328}'), ('Ě', 'E', '\u{30c}'), ('ě', 'e', '\u{30c}'), ('Ĝ', 'G', '\u{302}'),
+    ('ĝ', 'g', '\u{302}'), ('Ğ', 'G', '\u{306}'), ('ğ', 'g', '\u{306}'), ('Ġ', 'G', '\u{307}'),
+    ('ġ', 'g', '\u{307}'), ('Ģ', 'G', '\u{327}'), ('ģ', 'g', '\u{327}'), ('Ĥ', 'H', '\u{302}'),
+    ('ĥ', 'h', '\u{302}'), ('Ĩ', 'I', '\u{303}'), ('ĩ', 'i', '\u{303}'), ('Ī', 'I', '\u{304}'),
+    ('ī', 'i', '\u{304}'), ('Ĭ', 'I', '\u{306}'), ('ĭ', 'i', '\u{306}'), ('Į', 'I', '\u{328}'),
+    ('į', 'i', '\u{328}'), ('İ', 'I', '\u{307}'), ('Ĵ', 'J', '\u{302}'), ('ĵ', 'j', '\u{302}'),
+    ('Ķ', 'K', '\u{327}'), ('ķ', 'k', '\u{327}'), ('Ĺ', 'L', '\u{301}'), ('ĺ', 'l', '\u{301}'),
+    ('Ļ', 'L', '\u{327}'), ('ļ', 'l', '\u{327}'), ('Ľ', 'L', '\u{30c}'), ('ľ', 'l', '\u{30c}'),
+    ('Ń', 'N', '\u{301}'), ('ń', 'n', '\u{301}'), ('Ņ', 'N', '\u{327}'), ('ņ', 'n', '\u{327}'),
+    ('Ň', 'N', '\u{30c}'), ('ň', 'n', '\u{30c}'), ('Ō', 'O', '\u{304}'), ('ō', 'o', '\u{304}'),
+    ('Ŏ', 'O', '\u{306}'), ('ŏ', 'o', '\u{306}'), ('Ő', 'O', '\u{30b}'), ('ő', 'o', '\u{30b}'),
+    ('Ŕ', 'R', '\u{301}'), ('ŕ', 'r', '\u{301}'), ('Ŗ', 'R', '\u{327}'), ('ŗ', 'r', '\u{327}'),
+    ('Ř', 'R', '\u{30c}'), ('ř', 'r', '\u{30c}'), ('Ś', 'S', '\u{301}'), ('ś', 's', '\u{301}'),
+    ('Ŝ', 'S', '\u{302}'), ('ŝ', 's', '\u{302}'), ('Ş', 'S', '\u{327}'), ('ş', 's', '\u{327}'),
+    ('Š', 'S', '\u{30c}'), ('š', 's', '\u{30c}'), ('Ţ', 'T', '\u{327}'), ('ţ', 't', '\u{327}'),
+    ('Ť', 'T', '\u{30c}'), ('ť', 't', '\u{30c}'), ('Ũ', 'U', '\u{303}'), ('ũ', 'u', '\u{303}'),
+    ('Ū', 'U', '\u{304}'), ('ū', 'u', '\u{304}'), ('Ŭ', 'U', '\u{306}'), ('ŭ', 'u', '\u{306}'),
+    ('Ů', 'U', '\u{30a}'), ('ů', 'u', '\u{30a}'), ('Ű', 'U', '\u{30b}'), ('ű', 'u', '\u{30b}'),
+    ('Ų', 'U', '\u{328}'), ('ų', 'u', '\u{328}'), ('Ŵ', 'W', '\u{302}'), ('ŵ', 'w', '\u{302}'),
+    ('Ŷ', 'Y', '\u{302}'), ('ŷ', 'y', '\u{302}'), ('Ÿ', 'Y', '\u{308}'), ('Ź', 'Z', '\u{301}'),
+    ('ź', 'z', '\u{301}'), ('Ż', 'Z', '\u{307}'), ('ż', 'z', '\u{307}'), ('Ž', 'Z', '\u{30c}'),
+    ('ž', 'z', '\u{30c}'), ('Ơ', 'O', '\u{31b}'), ('ơ', 'o', '\u{31b}'), ('Ư', 'U', '\u{31b}'),
+    ('ư', 'u', '\u{31b}'), ('Ǎ', 'A', '\u{30c}'), ('ǎ', 'a', '\u{30c}'), ('Ǐ', 'I', '\u{30c}'),
+    ('ǐ', 'i', '\u{30c}'), ('Ǒ', 'O', '\u{30c}'), ('ǒ', 'o', '\u{30c}'), ('Ǔ', 'U', '\u{30c}'),
+    ('ǔ', 'u', '\u{30c}'), ('Ǖ', 'Ü', '\u{304}'), ('ǖ', 'ü', '\u{304}'), ('Ǘ', 'Ü', '\u{301}'),
+    ('ǘ', 'ü', '\u{301}'), ('Ǚ', 'Ü', '\u{30c}'), ('ǚ', 'ü', '\u{30c}'), ('Ǜ', 'Ü', '\u{300}'),
+    ('ǜ', 'ü', '\u{300}'), ('Ǟ', 'Ä', '\u{304}'), ('ǟ', 'ä', '\u{304}'), ('Ǡ', 'Ȧ', '\u{304}'),
+    ('ǡ', 'ȧ', '\u{304}'), ('Ǣ', 'Æ', '\u{304}'), ('ǣ', 'æ', '\u{304}'), ('Ǧ', 'G', '\u{30c}'),
+    ('ǧ', 'g', '\u{30c}'), ('Ǩ', 'K', '\u{30c}'), ('ǩ', 'k', '\u{30c}'), ('Ǫ', 'O', '\u{328}'),
+    ('ǫ', 'o', '\u{328}'), ('Ǭ', 'Ǫ', '\u{304}'), ('ǭ', 'ǫ', '\u{304}'), ('Ǯ', 'Ʒ', '\u{30c}'),
+    ('ǯ', 'ʒ', '\u{30c}'), ('ǰ', 'j', '\u{30c}'), ('Ǵ', 'G', '\u{301}'), ('ǵ', 'g', '\u{301}'),
+    ('Ǹ', 'N', '\u{300}'), ('ǹ', 'n', '\u{300}'), ('Ǻ', 'Å', '\u{301}'), ('ǻ', 'å', '\u{301}'),
+    ('Ǽ', 'Æ', '\u{301}'), ('ǽ', 'æ', '\u{301}'), ('Ǿ', 'Ø', '\u{301}'), ('ǿ', 'ø', '\u{301}'),
+    ('Ȁ', 'A', '\u{30f}'), ('ȁ', 'a', '\u{30f}'), ('Ȃ', 'A', '\u{311}'), ('ȃ', 'a', '\u{311}'),
+    ('Ȅ', 'E', '\u{30f}'), ('ȅ', 'e', '\u{30f}'), ('Ȇ', 'E', '\u{311}'), ('ȇ', 'e', '\u{311}'),
+    ('Ȉ', 'I', '\u{30f}'), ('ȉ', 'i', '\u{30f}'), ('Ȋ', 'I', '\u{311}'), ('ȋ', 'i', '\u{311}'),
+    ('Ȍ', 'O', '\u{30f}'), ('ȍ', 'o', '\u{30f}'), ('Ȏ', 'O', '\u{311}'), ('ȏ', 'o', '\u{311}'),
+    ('Ȑ', 'R', '\u{30f}'), ('ȑ', 'r', '\u{30f}'), ('Ȓ', 'R', '\u{311}'), ('ȓ', 'r', '\u{311}'),
+    ('Ȕ', 'U', '\u{30f}'), ('ȕ', 'u', '\u{30f}'), ('Ȗ', 'U', '\u{311}'), ('ȗ', 'u', '\u{311}'),
+    ('Ș', 'S', '\u{326}'), ('ș', 's', '\u{326}'), ('Ț', 'T', '\u{326}'), ('ț', 't', '\u{326}'),
+    ('Ȟ', 'H', '\u{30c}'), ('ȟ', 'h', '\u{30c}'), ('Ȧ', 'A', '\u{307}'), ('ȧ', 'a', '\u{307}'),
+    ('Ȩ', 'E', '\u{327}'), ('ȩ', 'e', '\u{327}'), ('Ȫ', 'Ö', '\u{304}'), ('ȫ', 'ö', '\u{304}'),
+    ('Ȭ', 'Õ', '\u{304}'), ('ȭ', 'õ', '\u{304}'), ('Ȯ', 'O', '\u{307}'), ('ȯ', 'o', '\u{307}'),
+    ('Ȱ', 'Ȯ', '\u{304}'), ('ȱ', 'ȯ', '\u{304}'), ('Ȳ', 'Y', '\u{304}'), ('ȳ', 'y', '\u{304}'),
+    ('Ḁ', 'A', '\u{325}'), ('ḁ', 'a', '\u{325}'), ('Ḃ', 'B', '\u{307}'), ('ḃ', 'b', '\u{307}'),
+    ('Ḅ', 'B', '\u{323}'), ('ḅ', 'b', '\u{323}'), ('Ḇ', 'B', '\u{331}'), ('ḇ', 'b', '\u{331}'),
+    ('Ḉ', 'Ç', '\u{301}'), ('ḉ', 'ç', '\u{301}'), ('Ḋ', 'D', '\u{307}'), ('ḋ', 'd', '\u{307}'),
+    ('Ḍ', 'D', '\u{323}'), ('ḍ', 'd', '\u{323}'), ('Ḏ', 'D', '\u{331}'), ('ḏ', 'd', '\u{331}'),
+    ('Ḑ', 'D', '\u{327}'), ('ḑ', 'd', '\u{327}'), ('Ḓ', 'D', '\u{32d}'), ('ḓ', 'd', '\u{32d}'),
+    ('Ḕ', 'Ē', '\u{300}'), ('ḕ', 'ē', '\u{300}'), ('Ḗ', 'Ē', '\u{301}'), ('ḗ', 'ē', '\u{301}'),
+    ('Ḙ', 'E', '\u{32d}'), ('ḙ', 'e', '\u{32d}'), ('Ḛ', 'E', '\u{330}'), ('ḛ', 'e', '\u{330}'),
+    ('Ḝ', 'Ȩ', '\u{306}'), ('ḝ', 'ȩ', '\u{306}'), ('Ḟ', 'F', '\u{307}'), ('ḟ', 'f', '\u{307}'),
+    ('Ḡ', 'G', '\u{304}'), ('ḡ', 'g', '\u{304}'), ('Ḣ', 'H', '\u{307}'), ('ḣ', 'h', '\u{307}'),
+    ('Ḥ', 'H', '\u{323}'), ('ḥ', 'h', '\u{323}'), ('Ḧ', 'H', '\u{308}'), ('ḧ', 'h', '\u{308}'),
+    ('Ḩ', 'H', '\u{327}'), ('ḩ', 'h', '\u{327}'), ('Ḫ', 'H', '\u{32e}'), ('ḫ', 'h', '\u{32e}'),
+    ('Ḭ', 'I', '\u{330}'), ('ḭ', 'i', '\u{330}'), ('Ḯ', 'Ï', '\u{301}'), ('ḯ', 'ï', '\u{301}'),
+    ('Ḱ', 'K', '\u{301}'), ('ḱ', 'k', '\u{301}'), ('Ḳ', 'K', '\u{323}'), ('ḳ', 'k', '\u{323}'),
+    ('Ḵ', 'K', '\u{331}'), ('ḵ', 'k', '\u{331}'), ('Ḷ', 'L', '\u{323}'), ('ḷ', 'l', '\u{323}'),
+    ('Ḹ', 'Ḷ', '\u{304}'), ('ḹ', 'ḷ', '\u{304}'), ('Ḻ', 'L', '\u{331}'), ('ḻ', 'l', '\u{331}'),
+    ('Ḽ', 'L', '\u{32d}'), ('ḽ', 'l', '\u{32d}'), ('Ḿ', 'M', '\u{301}'), ('ḿ', 'm', '\u{301}'),
+    ('Ṁ', 'M', '\u{307}'), ('ṁ', 'm', '\u{307}'), ('Ṃ', 'M', '\u{323}'), ('ṃ', 'm', '\u{323}'),
+    ('Ṅ', 'N', '\u{307}'), ('ṅ', 'n', '\u{307}'), ('Ṇ', 'N', '\u{323}'), ('ṇ', 'n', '\u{323}'),
+    ('Ṉ', 'N', '\u{331}'), ('ṉ', 'n', '\u{331}'), ('Ṋ', 'N', '\u{32d}'), ('ṋ', 'n', '\u{32d}'),
+    ('Ṍ', 'Õ', '\u{301}'), ('ṍ', 'õ', '\u{301}'), ('Ṏ', 'Õ', '\u{308}'), ('ṏ', 'õ', '\u{308}'),
+    ('Ṑ', 'Ō', '\u{300}'), ('ṑ', 'ō', '\u{300}'), ('Ṓ', 'Ō', '\u{301}'), ('ṓ', 'ō', '\u{301}'),
+    ('Ṕ', 'P', '\u{301}'), ('ṕ', 'p', '\u{301}'), ('Ṗ', 'P', '\u{307}'), ('ṗ', 'p', '\u{307}'),
+    ('Ṙ', 'R', '\u{307}'), ('ṙ', 'r', '\u{307}'), ('Ṛ', 'R', '\u{323}'), ('ṛ', 'r', '\u{323}'),
+    ('Ṝ', 'Ṛ', '\u{304}'), ('ṝ', 'ṛ', '\u{304}'), ('Ṟ', 'R', '\u{331}'), ('ṟ', 'r', '\u{331}'),
+    ('Ṡ', 'S', '\u{307}'), ('ṡ', 's', '\u{307}'), ('Ṣ', 'S', '\u{323}'), ('ṣ', 's', '\u{323}'),
+    ('Ṥ', 'Ś', '\u{307}'), ('ṥ', 'ś', '\u{307}'), ('Ṧ', 'Š', '\u{307}'), ('ṧ', 'š', '\u{307}'),
+    ('Ṩ', 'Ṣ', '\u{307}'), ('ṩ', 'ṣ', '\u{307}'), ('Ṫ', 'T', '\u{307}'), ('ṫ', 't', '\u{307}'),
+    ('Ṭ', 'T', '\u{323}'), ('ṭ', 't', '\u{323}'), ('Ṯ', 'T', '\u{331}'), ('ṯ', 't', '\u{331}'),
+    ('Ṱ', 'T', '\u{32d}'), ('ṱ', 't', '\u{32d}'), ('Ṳ', 'U', '\u{324}'), ('ṳ', 'u', '\u{324}'),
+    ('Ṵ', 'U', '\u{330}'), ('ṵ', 'u', '\u{330}'), ('Ṷ', 'U', '\u{32d}'), ('ṷ', 'u', '\u{32d}'),
+    ('Ṹ', 'Ũ', '\u{301}'), ('ṹ', 'ũ', '\u{301}'), ('Ṻ', 'Ū', '\u{308}'), ('ṻ', 'ū', '\u{308}'),
+    ('Ṽ', 'V', '\u{303}'), ('ṽ', 'v', '\u{303}'), ('Ṿ', 'V', '\u{323}'), ('ṿ', 'v', '\u{323}'),
+    ('Ẁ', 'W', '\u{300}'), ('ẁ', 'w', '\u{300}'), ('Ẃ', 'W', '\u{301}'), ('ẃ', 'w', '\u{301}'),
+    ('Ẅ', 'W', '\u{308}'), ('ẅ', 'w', '\u{308}'), ('Ẇ', 'W', '\u{307}'), ('ẇ', 'w', '\u{307}'),
+    ('Ẉ', 'W', '\u{323}'), ('ẉ', 'w', '\u{323}'), ('Ẋ', 'X', '\u{307}'), ('ẋ', 'x', '\u{307}'),
+    ('Ẍ', 'X', '\u{308}'), ('ẍ', 'x', '\u{308}'), ('Ẏ', 'Y', '\u{307}'), ('ẏ', 'y', '\u{307}'),
+    ('Ẑ', 'Z', '\u{302}'), ('ẑ', 'z', '\u{302}'), ('Ẓ', 'Z', '\u{323}'), ('ẓ', 'z', '\u{323}'),
+    ('Ẕ', 'Z', '\u{331}'), ('ẕ', 'z', '\u{331}'), ('ẖ', 'h', '\u{331}'), ('ẗ', 't', '\u{308}'),
+    ('ẘ', 'w', '\u{30a}'), ('ẙ', 'y', '\u{30a}'), ('ẛ', 'ſ', '\u{307}'), ('Ạ', 'A', '\u{323}'),
+    ('ạ', 'a', '\u{323}'), ('Ả', 'A', '\u{309}'), ('ả', 'a', '\u{309}'), ('Ấ', 'Â', '\u{301}'),
+    ('ấ', 'â', '\u{301}'), ('Ầ', 'Â', '\u{300}'), ('ầ', 'â', '\u{300}'), ('Ẩ', 'Â', '\u{309}'),
+    ('ẩ', 'â', '\u{309}'), ('Ẫ', 'Â', '\u{303}'), ('ẫ', 'â', '\u{303}'), ('Ậ', 'Ạ', '\u{302}'),
+    ('ậ', 'ạ', '\u{302}'), ('Ắ', 'Ă', '\u{301}'), ('ắ', 'ă', '\u{301}'), ('Ằ', 'Ă', '\u{300}'),
+    ('ằ', 'ă', '\u{300}'), ('Ẳ', 'Ă', '\u{309}'), ('ẳ', 'ă', '\u{309}'), ('Ẵ', 'Ă', '\u{303}'),
+    ('ẵ', 'ă', '\u{303}'), ('Ặ', 'Ạ', '\u{306}'), ('ặ', 'ạ', '\u{306}'), ('Ẹ', 'E', '\u{323}'),
+    ('ẹ', 'e', '\u{323}'), ('Ẻ', 'E', '\u{309}'), ('ẻ', 'e', '\u{309}'), ('Ẽ', 'E', '\u{303}'),
+    ('ẽ', 'e', '\u{303}'), ('Ế', 'Ê', '\u{301}'), ('ế', 'ê', '\u{301}'), ('Ề', 'Ê', '\u{300}'),
+    ('ề', 'ê', '\u{300}'), ('Ể', 'Ê', '\u{309}'), ('ể', 'ê', '\u{309}'), ('Ễ', 'Ê', '\u{303}'),
+    ('ễ', 'ê', '\u{303}'), ('Ệ', 'Ẹ', '\u{302}'), ('ệ', 'ẹ', '\u{302}'), ('Ỉ', 'I', '\u{309}'),
+    ('ỉ', 'i', '\u{309}'), ('Ị', 'I', '\u{323}'), ('ị', 'i', '\u{323}'), ('Ọ', 'O', '\u{323}'),
+    ('ọ', 'o', '\u{323}'), ('Ỏ', 'O', '\u{309}'), ('ỏ', 'o', '\u{309}'), ('Ố', 'Ô', '\u{301}'),
+    ('ố', 'ô', '\u{301}'), ('Ồ', 'Ô', '\u{300}'), ('ồ', 'ô', '\u{300}'), ('Ổ', 'Ô', '\u{309}'),
+    ('ổ', 'ô', '\u{309}'), ('Ỗ', 'Ô', '\u{303}'), ('ỗ', 'ô', '\u{303}'), ('Ộ', 'Ọ', '\u{302}'),
+    ('ộ', 'ọ', '\u{302}'), ('Ớ', 'Ơ', '\u{301}'), ('ớ', 'ơ', '\u{301}'), ('Ờ', 'Ơ', '\u{300}'),
+    ('ờ', 'ơ', '\u{300}'), ('Ở', 'Ơ', '\u{309}'), ('ở', 'ơ', '\u{309}'), ('Ỡ', 'Ơ', '\u{303}'),
+    ('ỡ', 'ơ', '\u{303}'), ('Ợ', 'Ơ', '\u{323}'), ('ợ', 'ơ', '\u{323}'), ('Ụ', 'U', '\u{323}'),
+    ('ụ', 'u', '\u{323}'), ('Ủ', 'U', '\u{309}'), ('ủ', 'u', '\u{309}'), ('Ứ', 'Ư', '\u{301}'),
+    ('ứ', 'ư', '\u{301}'), ('Ừ', 'Ư', '\u{300}'), ('ừ', 'ư', '\u{300}'), ('Ử', 'Ư', '\u{309}'),
+    ('ử', 'ư', '\u{309}'), ('Ữ', 'Ư', '\u{303}'), ('ữ', 'ư', '\u{303}'), ('Ự', 'Ư', '\u{323}'),
+    ('ự', 'ư', '\u{323}'), ('Ỳ', 'Y', '\u{300}'), ('ỳ', 'y', '\u{300}'), ('Ỵ', 'Y', '\u{323}'),
+    ('ỵ', 'y', '\u{323}'), ('Ỷ', 'Y', '\u{309}'), ('ỷ', 'y', '\u{309}'), ('Ỹ', 'Y', '\u{303}'),
+    ('ỹ', 'y', '\u{303}'), ('が', 'か', '\u{3099}'), ('ぎ', 'き', '\u{3099}'), ('ぐ', 'く', '\u{3099}'),
+    ('げ', 'け', '\u{3099}'), ('ご', 'こ', '\u{3099}'), ('ざ', 'さ', '\u{3099}'), ('じ', 'し', '\u{3099}'),
+    ('ず', 'す', '\u{3099}'), ('ぜ', 'せ', '\u{3099}'), ('ぞ', 'そ', '\u{3099}'), ('だ', 'た', '\u{3099}'),
+    ('ぢ', 'ち', '\u{3099}'), ('づ', 'つ', '\u{3099}'), ('で', 'て', '\u{3099}'), ('ど', 'と', '\u{3099}'),
+    ('ば', 'は', '\u{3099}'), ('ぱ', 'は', '\u{309a}'), ('び', 'ひ', '\u{3099}'), ('ぴ', 'ひ', '\u{309a}'),
+    ('ぶ', 'ふ', '\u{3099}'), ('ぷ', 'ふ', '\u{309a}'), ('べ', 'へ', '\u{3099}'), ('ぺ', 'へ', '\u{309a}'),
+    ('ぼ', 'ほ', '\u{3099}'), ('ぽ', 'ほ', '\u{309a}'), ('ゔ', 'う', '\u{3099}'), ('ゞ', 'ゝ', '\u{3099}'),
+    ('ガ', 'カ', '\u{3099}'), ('ギ', 'キ', '\u{3099}'), ('グ', 'ク', '\u{3099}'), ('ゲ', 'ケ', '\u{3099}'),
+    ('ゴ', 'コ', '\u{3099}'), ('ザ', 'サ', '\u{3099}'), ('ジ', 'シ', '\u{3099}'), ('ズ', 'ス', '\u{3099}'),
+    ('ゼ', 'セ', '\u{3099}'), ('ゾ', 'ソ', '\u{3099}'), ('ダ', 'タ', '\u{3099}'), ('ヂ', 'チ', '\u{3099}'),
+    ('ヅ', 'ツ', '\u{3099}'), ('デ', 'テ', '\u{3099}'), ('ド', 'ト', '\u{3099}'), ('バ', 'ハ', '\u{3099}'),
+    ('パ', 'ハ', '\u{309a}'), ('ビ', 'ヒ', '\u{3099}'), ('ピ', 'ヒ', '\u{309a}'), ('ブ', 'フ', '\u{3099}'),
+    ('プ', 'フ', '\u{309a}'), ('ベ', 'ヘ', '\u{3099}'), ('ペ', 'ヘ', '\u{309a}'), ('ボ', 'ホ', '\u{3099}'),
+    ('ポ', 'ホ', '\u{309a}'), ('ヴ', 'ウ', '\u{3099}'), ('ヷ', 'ワ', '\u{3099}'), ('ヸ', 'ヰ', '\u{3099}'),
+    ('ヹ', 'ヱ', '\u{3099}'), ('ヺ', 'ヲ', '\u{3099}'), ('ヾ', 'ヽ', '\u{3099}'),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(normalize("café", NormalizationForm::Nfd), "cafe\u{301}");
+        assert_eq!(normalize("cafe\u{301}", NormalizationForm::Nfc), "café");
+        // Two levels: `ǖ` is `ü` with a macron.
+        assert_eq!(normalize("ǖ", NormalizationForm::Nfd), "u\u{308}\u{304}");
+        assert_eq!(normalize("u\u{308}\u{304}", NormalizationForm::Nfc), "ǖ");
+        assert_eq!(normalize("か\u{3099}き", NormalizationForm::Nfc), "がき");
+    }
+}
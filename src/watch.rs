@@ -0,0 +1,242 @@
+use std::io::{self, Write};
+use std::process::Command;
+
+use crate::parser::parse_lines;
+use crate::tree_generator::{generate_tree_from_paths, Options};
+
+pub const DEFAULT_WATCH_COMMAND: &str = "git status --porcelain";
+
+/// Runs a command and hands back what it printed, so tests can swap in canned output.
+pub trait CommandRunner {
+    fn output(&mut self, command: &mut Command) -> io::Result<String>;
+}
+
+pub struct SystemRunner;
+
+impl CommandRunner for SystemRunner {
+    fn output(&mut self, command: &mut Command) -> io::Result<String> {
+        let output = command.output()?;
+        command_output(output.status.code(), &output.stdout, &output.stderr)
+    }
+}
+
+/// What a command printed, or, if it did not exit with 0, an error carrying what it printed to
+/// stderr. `code` is `None` for a command killed by a signal.
+fn command_output(code: Option<i32>, stdout: &[u8], stderr: &[u8]) -> io::Result<String> {
+    if code == Some(0) {
+        return Ok(String::from_utf8_lossy(stdout).into_owned());
+    }
+    let status = code.map_or("was killed".to_string(), |code| {
+        format!("exited with {}", code)
+    });
+    let stderr = String::from_utf8_lossy(stderr);
+    Err(io::Error::other(match stderr.trim() {
+        "" => status,
+        stderr => format!("{}: {}", status, stderr),
+    }))
+}
+
+fn shell_command(command_line: &str) -> Command {
+    let mut command = if cfg!(windows) {
+        let mut command = Command::new("cmd");
+        command.arg("/C");
+        command
+    } else {
+        let mut command = Command::new("sh");
+        command.arg("-c");
+        command
+    };
+    command.arg(command_line);
+    command
+}
+
+/// Splits a `--status-cmd` template into words the way a shell would, with `'…'`, `"…"` and
+/// `\` quoting, and fills `{repo}` in each of them with `repo`. No shell runs the command, so
+/// a `repo` with spaces or quotes in it stays one argument.
+pub fn template_command(template: &str, repo: &str) -> Result<Command, String> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut chars = template.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => words.extend(word.take()),
+            '\'' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => word.push(c),
+                        None => return Err("unclosed `'`".to_string()),
+                    }
+                }
+            }
+            '"' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c @ ('"' | '\\')) => word.push(c),
+                            Some(c) => word.extend(['\\', c]),
+                            None => return Err("unclosed `\"`".to_string()),
+                        },
+                        Some(c) => word.push(c),
+                        None => return Err("unclosed `\"`".to_string()),
+                    }
+                }
+            }
+            '\\' => {
+                let c = chars.next().ok_or("trailing `\\`")?;
+                word.get_or_insert_with(String::new).push(c);
+            }
+            c => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    words.extend(word);
+    let mut words = words.into_iter().map(|word| word.replace("{repo}", repo));
+    let mut command = Command::new(words.next().ok_or("empty command")?);
+    command.args(words);
+    Ok(command)
+}
+
+/// Runs the `--status-cmd` template for `repo` and returns what it printed.
+pub fn read_status_command(
+    runner: &mut impl CommandRunner,
+    template: &str,
+    repo: &str,
+) -> io::Result<String> {
+    let mut command = template_command(template, repo)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+    runner.output(&mut command)
+}
+
+/// Re-runs `command_line` and redraws its output as a tree, one frame per call.
+pub struct Watcher<'a, R: CommandRunner> {
+    pub command_line: &'a str,
+    pub options: &'a Options,
+    pub runner: R,
+}
+
+impl<R: CommandRunner> Watcher<'_, R> {
+    pub fn render_frame(&mut self, out: &mut impl Write) -> io::Result<()> {
+        let output = self.runner.output(&mut shell_command(self.command_line))?;
+        let lines: Vec<String> = output.lines().map(str::to_string).collect();
+        let tree = generate_tree_from_paths(&parse_lines(&lines), self.options);
+        // The cursor is only hidden while a frame is being drawn, so interrupting the
+        // watch with Ctrl-C during the wait never leaves the terminal without one.
+        write!(out, "\x1b[?25l\x1b[H\x1b[2J{}\x1b[?25h", tree)?;
+        out.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct FakeRunner {
+        outputs: Vec<&'static str>,
+        commands: Vec<String>,
+        programs: Vec<Vec<String>>,
+        /// The exit code and stderr of a command that fails.
+        failure: Option<(i32, &'static str)>,
+    }
+
+    impl CommandRunner for FakeRunner {
+        fn output(&mut self, command: &mut Command) -> io::Result<String> {
+            let args: Vec<_> = command.get_args().map(|a| a.to_string_lossy()).collect();
+            self.commands.push(args.last().unwrap().to_string());
+            let program = command.get_program().to_string_lossy().to_string();
+            self.programs.push(
+                std::iter::once(program)
+                    .chain(args.iter().map(|arg| arg.to_string()))
+                    .collect(),
+            );
+            match self.failure {
+                Some((code, stderr)) => command_output(Some(code), b"", stderr.as_bytes()),
+                None => command_output(Some(0), self.outputs.remove(0).as_bytes(), b""),
+            }
+        }
+    }
+
+    #[test]
+    fn test_watch_renders_successive_frames() {
+        let mut watcher = Watcher {
+            command_line: DEFAULT_WATCH_COMMAND,
+            options: &Options::default(),
+            runner: FakeRunner {
+                outputs: vec![" M a.txt\n", " M a.txt\n?? b.txt\n"],
+                commands: Vec::new(),
+                programs: Vec::new(),
+                failure: None,
+            },
+        };
+        let mut out = Vec::new();
+        watcher.render_frame(&mut out).unwrap();
+        watcher.render_frame(&mut out).unwrap();
+
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "\x1b[?25l\x1b[H\x1b[2J└── a.txt\n\x1b[?25h\x1b[?25l\x1b[H\x1b[2J├── a.txt\n└── b.txt\n\x1b[?25h"
+        );
+        assert_eq!(
+            watcher.runner.commands,
+            vec![DEFAULT_WATCH_COMMAND, DEFAULT_WATCH_COMMAND]
+        );
+    }
+
+    #[test]
+    fn test_read_status_command() {
+        let mut runner = FakeRunner {
+            outputs: vec![" M src/main.rs\n?? notes.txt\n"],
+            commands: Vec::new(),
+            programs: Vec::new(),
+            failure: None,
+        };
+        let output = read_status_command(
+            &mut runner,
+            "git -C {repo} status --porcelain --untracked-files='all'",
+            "my repo",
+        )
+        .unwrap();
+        assert_eq!(
+            runner.programs,
+            vec![vec![
+                "git",
+                "-C",
+                "my repo",
+                "status",
+                "--porcelain",
+                "--untracked-files=all"
+            ]]
+        );
+        let lines: Vec<String> = output.lines().map(str::to_string).collect();
+        assert_eq!(
+            generate_tree_from_paths(&parse_lines(&lines), &Options::default()),
+            "├── notes.txt\n└── src\n    └── main.rs\n"
+        );
+    }
+
+    #[test]
+    fn test_read_status_command_failure() {
+        let mut runner = FakeRunner {
+            outputs: Vec::new(),
+            commands: Vec::new(),
+            programs: Vec::new(),
+            failure: Some((128, "fatal: cannot change to '/nonexistent'\n")),
+        };
+        let err =
+            read_status_command(&mut runner, "git -C {repo} status", "/nonexistent").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "exited with 128: fatal: cannot change to '/nonexistent'"
+        );
+    }
+
+    #[test]
+    fn test_template_command_errors() {
+        let error = |template| template_command(template, ".").unwrap_err();
+        assert_eq!(error(""), "empty command");
+        assert_eq!(error("hg status 'x"), "unclosed `'`");
+        assert_eq!(error(r#"jj "st"#), "unclosed `\"`");
+    }
+}